@@ -27,7 +27,7 @@ fn main() {
         .read_line(&mut selection)
         .expect("error: unable to read user input");
     for dev in devices.clone() {
-        if let true = selection.trim() == dev.index.to_string() {
+        if selection.trim() == dev.index.to_string() {
             handler.increase_device_volume_by_percent(dev.index, 0.05);
         }
     }
@@ -0,0 +1,680 @@
+/// Command line front-end for the `pulsectl` library
+///
+/// Devices can be given by index, by name or by a part of their description, product or port,
+/// streams by index or by a part of their application name.
+/// Pass `--json` to any subcommand to get machine readable output.
+extern crate clap;
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+extern crate serde_json;
+
+use std::process;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use pulse::volume::{ChannelVolumes, Volume, VOLUME_MAX, VOLUME_NORM};
+use serde_json::{json, Value};
+
+use pulsectl::controllers::types::{ApplicationInfo, CardInfo, DeviceInfo, ServerInfo};
+use pulsectl::controllers::{
+    AppControl, CardController, DeviceControl, SinkController, SourceController,
+};
+use pulsectl::events::{subscription_masks, Event, Facility};
+use pulsectl::{Handler, HandlerBuilder, PulseCtl};
+
+type CliResult = Result<(), String>;
+
+fn main() {
+    let device_kinds = ["sink", "source"];
+    let stream_kinds = ["sink-input", "source-output"];
+    let all_kinds = ["sink", "source", "sink-input", "source-output"];
+
+    let matches = App::new("pulsectl")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Control PulseAudio devices and applications")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .global(true)
+                .help("Print machine readable JSON instead of text"),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List devices, applications or cards")
+                .arg(
+                    Arg::with_name("what")
+                        .required(true)
+                        .possible_values(&["sinks", "sources", "apps", "cards"]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set-volume")
+                .about("Set the volume of a device or stream, e.g. 50%, +5% or -10%")
+                .arg(
+                    Arg::with_name("kind")
+                        .required(true)
+                        .possible_values(&all_kinds),
+                )
+                .arg(Arg::with_name("target").required(true))
+                .arg(
+                    Arg::with_name("volume")
+                        .required(true)
+                        .allow_hyphen_values(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("mute")
+                .about("Mute, unmute or toggle a device or stream")
+                .arg(
+                    Arg::with_name("kind")
+                        .required(true)
+                        .possible_values(&all_kinds),
+                )
+                .arg(Arg::with_name("target").required(true))
+                .arg(
+                    Arg::with_name("state")
+                        .possible_values(&["on", "off", "toggle"])
+                        .default_value("toggle"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("move")
+                .about("Move a stream to another device")
+                .arg(
+                    Arg::with_name("kind")
+                        .required(true)
+                        .possible_values(&stream_kinds),
+                )
+                .arg(Arg::with_name("stream").required(true))
+                .arg(Arg::with_name("device").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("set-default")
+                .about("Make a device the default")
+                .arg(
+                    Arg::with_name("kind")
+                        .required(true)
+                        .possible_values(&device_kinds),
+                )
+                .arg(Arg::with_name("device").required(true)),
+        )
+        .subcommand(SubCommand::with_name("info").about("Show information about the server"))
+        .subcommand(SubCommand::with_name("watch").about("Print change events as they happen"))
+        .get_matches();
+
+    let json = matches.is_present("json");
+    let result = match matches.subcommand() {
+        ("list", Some(args)) => list(args, json),
+        ("set-volume", Some(args)) => set_volume(args, json),
+        ("mute", Some(args)) => mute(args, json),
+        ("move", Some(args)) => move_stream(args, json),
+        ("set-default", Some(args)) => set_default(args, json),
+        ("info", Some(_)) => info(json),
+        ("watch", Some(_)) => watch(json),
+        _ => unreachable!(),
+    };
+    if let Err(message) = result {
+        eprintln!("pulsectl: {}", message);
+        process::exit(1);
+    }
+}
+
+fn list(args: &ArgMatches, json: bool) -> CliResult {
+    match args.value_of("what").unwrap() {
+        "sinks" => {
+            let devices = sinks()?.list_devices().map_err(|e| format!("{:?}", e))?;
+            print_list(&devices, json, device_json)
+        }
+        "sources" => {
+            let devices = sources()?.list_devices().map_err(|e| format!("{:?}", e))?;
+            print_list(&devices, json, device_json)
+        }
+        "apps" => {
+            let pulse = PulseCtl::connect("pulsectl").map_err(|e| format!("{:?}", e))?;
+            let mut apps = Vec::new();
+            for app in pulse
+                .sinks()
+                .list_applications()
+                .map_err(|e| format!("{:?}", e))?
+            {
+                apps.push(("sink-input", app));
+            }
+            for app in pulse
+                .sources()
+                .list_applications()
+                .map_err(|e| format!("{:?}", e))?
+            {
+                apps.push(("source-output", app));
+            }
+            if json {
+                let values: Vec<Value> = apps
+                    .iter()
+                    .map(|(kind, app)| {
+                        let mut value = app_json(app);
+                        value["kind"] = json!(kind);
+                        value
+                    })
+                    .collect();
+                println!("{}", Value::Array(values));
+            } else {
+                for (_, app) in apps {
+                    println!("{}", app);
+                }
+            }
+            Ok(())
+        }
+        "cards" => {
+            let cards = cards()?.list_cards().map_err(|e| format!("{:?}", e))?;
+            print_list(&cards, json, card_json)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn set_volume(args: &ArgMatches, json: bool) -> CliResult {
+    let target = args.value_of("target").unwrap();
+    let volume = args.value_of("volume").unwrap();
+    match args.value_of("kind").unwrap() {
+        "sink" => set_device_volume(&mut sinks()?, target, volume, json),
+        "source" => set_device_volume(&mut sources()?, target, volume, json),
+        "sink-input" => set_app_volume(&mut sinks()?, target, volume, json),
+        "source-output" => set_app_volume(&mut sources()?, target, volume, json),
+        _ => unreachable!(),
+    }
+}
+
+fn set_device_volume<C: DeviceControl<DeviceInfo>>(
+    controller: &mut C,
+    target: &str,
+    volume: &str,
+    json: bool,
+) -> CliResult {
+    let device = resolve_device(controller, target)?;
+    let volumes = apply_volume(device.volume, volume)?;
+    controller.set_device_volume_by_index(device.index, &volumes);
+    let device = controller
+        .get_device_by_index(device.index)
+        .map_err(|e| format!("{:?}", e))?;
+    print_one(&device, json, device_json)
+}
+
+fn set_app_volume<C: AppControl<ApplicationInfo>>(
+    controller: &mut C,
+    target: &str,
+    volume: &str,
+    json: bool,
+) -> CliResult {
+    let app = resolve_app(controller, target)?;
+    let volumes = apply_volume(app.volume, volume)?;
+    check(controller.set_app_volume(app.index, &volumes), "set volume")?;
+    let app = controller
+        .get_app_by_index(app.index)
+        .map_err(|e| format!("{:?}", e))?;
+    print_one(&app, json, app_json)
+}
+
+fn mute(args: &ArgMatches, json: bool) -> CliResult {
+    let target = args.value_of("target").unwrap();
+    let state = args.value_of("state").unwrap();
+    match args.value_of("kind").unwrap() {
+        "sink" => mute_device(&mut sinks()?, target, state, json),
+        "source" => mute_device(&mut sources()?, target, state, json),
+        "sink-input" => mute_app(&mut sinks()?, target, state, json),
+        "source-output" => mute_app(&mut sources()?, target, state, json),
+        _ => unreachable!(),
+    }
+}
+
+fn mute_device<C: DeviceControl<DeviceInfo>>(
+    controller: &mut C,
+    target: &str,
+    state: &str,
+    json: bool,
+) -> CliResult {
+    let device = resolve_device(controller, target)?;
+    let mute = mute_state(device.mute, state);
+    check(
+        controller.set_device_mute_by_index(device.index, mute),
+        "change mute state",
+    )?;
+    let device = controller
+        .get_device_by_index(device.index)
+        .map_err(|e| format!("{:?}", e))?;
+    print_one(&device, json, device_json)
+}
+
+fn mute_app<C: AppControl<ApplicationInfo>>(
+    controller: &mut C,
+    target: &str,
+    state: &str,
+    json: bool,
+) -> CliResult {
+    let app = resolve_app(controller, target)?;
+    let mute = mute_state(app.mute, state);
+    check(
+        controller.set_app_mute(app.index, mute),
+        "change mute state",
+    )?;
+    let app = controller
+        .get_app_by_index(app.index)
+        .map_err(|e| format!("{:?}", e))?;
+    print_one(&app, json, app_json)
+}
+
+fn move_stream(args: &ArgMatches, json: bool) -> CliResult {
+    let stream = args.value_of("stream").unwrap();
+    let device = args.value_of("device").unwrap();
+    match args.value_of("kind").unwrap() {
+        "sink-input" => move_app(&mut sinks()?, stream, device, json),
+        "source-output" => move_app(&mut sources()?, stream, device, json),
+        _ => unreachable!(),
+    }
+}
+
+fn move_app<C: AppControl<ApplicationInfo> + DeviceControl<DeviceInfo>>(
+    controller: &mut C,
+    stream: &str,
+    device: &str,
+    json: bool,
+) -> CliResult {
+    let app = resolve_app(controller, stream)?;
+    let device = resolve_device(controller, device)?;
+    check(
+        controller.move_app_by_index(app.index, device.index),
+        "move stream",
+    )?;
+    let app = controller
+        .get_app_by_index(app.index)
+        .map_err(|e| format!("{:?}", e))?;
+    print_one(&app, json, app_json)
+}
+
+fn set_default(args: &ArgMatches, json: bool) -> CliResult {
+    let target = args.value_of("device").unwrap();
+    match args.value_of("kind").unwrap() {
+        "sink" => set_default_device(&mut sinks()?, target, json),
+        "source" => set_default_device(&mut sources()?, target, json),
+        _ => unreachable!(),
+    }
+}
+
+fn set_default_device<C: DeviceControl<DeviceInfo>>(
+    controller: &mut C,
+    target: &str,
+    json: bool,
+) -> CliResult {
+    let device = resolve_device(controller, target)?;
+    let name = device
+        .name
+        .clone()
+        .ok_or_else(|| format!("device #{} has no name", device.index))?;
+    check(controller.set_default_device(&name), "set default device")?;
+    print_one(&device, json, device_json)
+}
+
+fn info(json: bool) -> CliResult {
+    let server = sinks()?.get_server_info().map_err(|e| format!("{:?}", e))?;
+    print_one(&server, json, server_json)
+}
+
+fn watch(json: bool) -> CliResult {
+    let handler = HandlerBuilder::new("pulsectl")
+        .reconnect(true)
+        .connect()
+        .map_err(|e| format!("{:?}", e))?;
+    let mut controller = SinkController::from_handler(handler);
+    controller
+        .handler
+        .subscribe(subscription_masks::ALL)
+        .map_err(|e| format!("{:?}", e))?;
+    loop {
+        let event = controller
+            .handler
+            .next_event()
+            .map_err(|e| format!("{:?}", e))?;
+        let operation = match event {
+            Event::New(..) => "new",
+            Event::Changed(..) => "change",
+            Event::Removed(..) => "remove",
+            Event::Reconnected => {
+                if json {
+                    println!("{}", json!({ "event": "reconnect" }));
+                } else {
+                    println!("Reconnected to the server");
+                }
+                continue;
+            }
+        };
+        let (facility, index) = match (event.facility(), event.index()) {
+            (Some(facility), Some(index)) => (facility_name(facility), index),
+            _ => continue,
+        };
+        if json {
+            println!(
+                "{}",
+                json!({ "event": operation, "facility": facility, "index": index })
+            );
+        } else {
+            println!("Event '{}' on {} #{}", operation, facility, index);
+        }
+    }
+}
+
+/// Find a device by index, or by the best match of its description, name, product or port
+fn resolve_device<C: DeviceControl<DeviceInfo>>(
+    controller: &mut C,
+    target: &str,
+) -> Result<DeviceInfo, String> {
+    if let Ok(index) = target.parse::<u32>() {
+        return controller
+            .get_device_by_index(index)
+            .map_err(|_| format!("no device with index {}", index));
+    }
+    let found = controller
+        .find_devices(target)
+        .map_err(|e| format!("{:?}", e))?;
+    // only the devices that matched as well as the best one are candidates
+    let best = match found.first() {
+        Some(first) => first.kind,
+        None => return Err(format!("no device matches '{}'", target)),
+    };
+    let matches: Vec<&DeviceInfo> = found
+        .iter()
+        .take_while(|m| m.kind == best)
+        .map(|m| &m.device)
+        .collect();
+    match matches.len() {
+        1 => Ok(matches[0].clone()),
+        _ => Err(format!(
+            "'{}' matches several devices: {}",
+            target,
+            matches
+                .iter()
+                .map(|d| format!(
+                    "#{} {}",
+                    d.index,
+                    d.description.as_deref().or(d.name.as_deref()).unwrap_or("")
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Find a stream by index or a case insensitive part of its application name
+fn resolve_app<C: AppControl<ApplicationInfo>>(
+    controller: &mut C,
+    target: &str,
+) -> Result<ApplicationInfo, String> {
+    if let Ok(index) = target.parse::<u32>() {
+        return controller
+            .get_app_by_index(index)
+            .map_err(|_| format!("no stream with index {}", index));
+    }
+    let needle = target.to_lowercase();
+    let apps = controller
+        .list_applications()
+        .map_err(|e| format!("{:?}", e))?;
+    let matches: Vec<&ApplicationInfo> = apps
+        .iter()
+        .filter(|a| {
+            a.proplist
+                .get_str(pulse::proplist::properties::APPLICATION_NAME)
+                .or_else(|| a.name.clone())
+                .is_some_and(|name| name.to_lowercase().contains(&needle))
+        })
+        .collect();
+    match matches.len() {
+        0 => Err(format!("no stream matches '{}'", target)),
+        1 => Ok(matches[0].clone()),
+        _ => Err(format!(
+            "'{}' matches several streams: {}",
+            target,
+            matches
+                .iter()
+                .map(|a| format!("#{}", a.index))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// A connection for a single command, a missing server is reported rather than panicking
+fn connect() -> Result<Handler, String> {
+    Handler::connect("pulsectl").map_err(|e| format!("{:?}", e))
+}
+
+fn sinks() -> Result<SinkController, String> {
+    Ok(SinkController::from_handler(connect()?))
+}
+
+fn sources() -> Result<SourceController, String> {
+    Ok(SourceController::from_handler(connect()?))
+}
+
+fn cards() -> Result<CardController, String> {
+    Ok(CardController::from_handler(connect()?))
+}
+
+/// Parse `50%`, `+5%` or `-5%` and apply it to the current volume of every channel
+fn apply_volume(mut current: ChannelVolumes, volume: &str) -> Result<ChannelVolumes, String> {
+    let invalid = || format!("invalid volume '{}', expected e.g. 50%, +5% or -5%", volume);
+    let number = volume.strip_suffix('%').unwrap_or(volume);
+    // at most one sign, and it has to be followed by the number itself
+    let (sign, magnitude) = match number.strip_prefix('+') {
+        Some(rest) => (Some('+'), rest),
+        None => match number.strip_prefix('-') {
+            Some(rest) => (Some('-'), rest),
+            None => (None, number),
+        },
+    };
+    if !magnitude.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return Err(invalid());
+    }
+    let percent: f64 = magnitude.parse().map_err(|_| invalid())?;
+    if !percent.is_finite() {
+        return Err(invalid());
+    }
+    let step = percent / 100.0 * f64::from(VOLUME_NORM.0);
+    for channel in current.get_mut() {
+        let new = match sign {
+            Some('+') => f64::from(channel.0) + step,
+            Some(_) => f64::from(channel.0) - step,
+            None => step,
+        };
+        *channel = Volume(new.max(0.0).min(f64::from(VOLUME_MAX.0)) as u32);
+    }
+    Ok(current)
+}
+
+fn mute_state(current: bool, state: &str) -> bool {
+    match state {
+        "on" => true,
+        "off" => false,
+        _ => !current,
+    }
+}
+
+fn check<E: std::fmt::Debug>(result: Result<bool, E>, action: &str) -> CliResult {
+    match result {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("server refused to {}", action)),
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+fn print_one<T: std::fmt::Display>(item: &T, json: bool, to_json: fn(&T) -> Value) -> CliResult {
+    if json {
+        println!("{}", to_json(item));
+    } else {
+        print!("{}", item);
+    }
+    Ok(())
+}
+
+fn print_list<T: std::fmt::Display>(
+    items: &[T],
+    json: bool,
+    to_json: fn(&T) -> Value,
+) -> CliResult {
+    if json {
+        println!("{}", Value::Array(items.iter().map(to_json).collect()));
+    } else {
+        for item in items {
+            println!("{}", item);
+        }
+    }
+    Ok(())
+}
+
+fn facility_name(facility: Facility) -> &'static str {
+    match facility {
+        Facility::Sink => "sink",
+        Facility::Source => "source",
+        Facility::SinkInput => "sink-input",
+        Facility::SourceOutput => "source-output",
+        Facility::Module => "module",
+        Facility::Client => "client",
+        Facility::SampleCache => "sample-cache",
+        Facility::Server => "server",
+        Facility::Card => "card",
+    }
+}
+
+fn volume_json(volume: &ChannelVolumes) -> Value {
+    let percent = |v: &Volume| (f64::from(v.0) * 100.0 / f64::from(VOLUME_NORM.0)).round();
+    json!({
+        "channels": volume.get().iter().map(|v| v.0).collect::<Vec<_>>(),
+        "percent": volume.get().iter().map(percent).collect::<Vec<_>>(),
+        "average_percent": percent(&volume.avg()),
+    })
+}
+
+fn proplist_json(proplist: &pulse::proplist::Proplist) -> Value {
+    let mut map = serde_json::Map::new();
+    for key in proplist.iter() {
+        if let Some(value) = proplist.get_str(&key) {
+            map.insert(key, Value::String(value));
+        }
+    }
+    Value::Object(map)
+}
+
+fn device_json(device: &DeviceInfo) -> Value {
+    json!({
+        "index": device.index,
+        "name": device.name,
+        "description": device.description,
+        "driver": device.driver,
+        "state": device.state.to_string(),
+        "mute": device.mute,
+        "volume": volume_json(&device.volume),
+        "sample_spec": device.sample_spec.print(),
+        "channel_map": device.channel_map.print(),
+        "owner_module": device.owner_module,
+        "card": device.card,
+        "monitor": device.monitor,
+        "monitor_name": device.monitor_name,
+        "latency_usec": device.latency.0,
+        "configured_latency_usec": device.configured_latency.0,
+        "ports": device.ports.iter().map(|p| json!({
+            "name": p.name,
+            "description": p.description,
+            "priority": p.priority,
+        })).collect::<Vec<_>>(),
+        "active_port": device.active_port.as_ref().and_then(|p| p.name.clone()),
+        "formats": device.formats.iter().map(|f| f.print()).collect::<Vec<_>>(),
+        "properties": proplist_json(&device.proplist),
+    })
+}
+
+fn app_json(app: &ApplicationInfo) -> Value {
+    json!({
+        "index": app.index,
+        "name": app.name,
+        "application": app.proplist.get_str(pulse::proplist::properties::APPLICATION_NAME),
+        "device": app.connection_id,
+        "client": app.client,
+        "owner_module": app.owner_module,
+        "driver": app.driver,
+        "mute": app.mute,
+        "corked": app.corked,
+        "volume": volume_json(&app.volume),
+        "sample_spec": app.sample_spec.print(),
+        "channel_map": app.channel_map.print(),
+        "format": app.format.print(),
+        "buffer_usec": app.buffer_usec.0,
+        "device_usec": app.connection_usec.0,
+        "properties": proplist_json(&app.proplist),
+    })
+}
+
+fn card_json(card: &CardInfo) -> Value {
+    json!({
+        "index": card.index,
+        "name": card.name,
+        "driver": card.driver,
+        "owner_module": card.owner_module,
+        "profiles": card.profiles.iter().map(|p| json!({
+            "name": p.name,
+            "description": p.description,
+            "sinks": p.n_sinks,
+            "sources": p.n_sources,
+            "priority": p.priority,
+            "available": p.available,
+        })).collect::<Vec<_>>(),
+        "active_profile": card.active_profile.as_ref().and_then(|p| p.name.clone()),
+        "ports": card.ports.iter().map(|p| json!({
+            "name": p.name,
+            "description": p.description,
+            "priority": p.priority,
+            "latency_offset_usec": p.latency_offset,
+        })).collect::<Vec<_>>(),
+        "properties": proplist_json(&card.proplist),
+    })
+}
+
+fn server_json(server: &ServerInfo) -> Value {
+    json!({
+        "user_name": server.user_name,
+        "host_name": server.host_name,
+        "server_name": server.server_name,
+        "server_version": server.server_version,
+        "sample_spec": server.sample_spec.print(),
+        "channel_map": server.channel_map.print(),
+        "default_sink": server.default_sink_name,
+        "default_source": server.default_source_name,
+        "cookie": server.cookie,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo(percent: f64) -> ChannelVolumes {
+        let mut volume = ChannelVolumes::default();
+        volume.set(
+            2,
+            Volume((f64::from(VOLUME_NORM.0) * percent / 100.0) as u32),
+        );
+        volume
+    }
+
+    #[test]
+    fn volumes_are_absolute_or_relative() {
+        let current = stereo(50.0);
+        assert_eq!(apply_volume(current, "40%").unwrap(), stereo(40.0));
+        assert_eq!(apply_volume(current, "+5%").unwrap(), stereo(55.0));
+        assert_eq!(apply_volume(current, "-5").unwrap(), stereo(45.0));
+        assert_eq!(apply_volume(current, "-80%").unwrap(), stereo(0.0));
+    }
+
+    #[test]
+    fn only_one_sign_is_accepted() {
+        let current = stereo(50.0);
+        for volume in &["+-5%", "--5%", "++5%", "-+5", "+", "5%%", "inf%", "loud"] {
+            assert!(apply_volume(current, volume).is_err(), "{}", volume);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pulse::{operation::Operation, volume::ChannelVolumes};
+
+use super::direction::{Direction, Done};
+use super::errors::ControllerError;
+use super::DeviceController;
+use crate::trace;
+use crate::{Handler, HandlerRef};
+
+/// Changes queued on a `DeviceController` that are answered together, with a single wait
+/// instead of a round-trip per change
+///
+/// Every change is sent to the server as soon as it is queued, `run` then waits for all the
+/// answers at once. Dropping the batch without running it still applies the changes, their
+/// outcome is just never looked at.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::{AppControl, SinkController};
+///
+/// fn main() {
+///     let mut sinks = SinkController::create();
+///     let apps = sinks.list_applications().unwrap();
+///
+///     let mut batch = sinks.batch();
+///     for app in &apps {
+///         batch = batch.set_app_mute(app.index, true);
+///     }
+///     for (app, result) in apps.iter().zip(batch.run()) {
+///         println!("{}: {:?}", app.index, result);
+///     }
+/// }
+/// ```
+pub struct Batch<'a, D, H> {
+    controller: &'a mut DeviceController<D, H>,
+    ops: Vec<Operation<dyn FnMut(bool)>>,
+    answers: Vec<Rc<RefCell<bool>>>,
+}
+
+impl<'a, D: Direction, H: HandlerRef> Batch<'a, D, H> {
+    pub(crate) fn new(controller: &'a mut DeviceController<D, H>) -> Self {
+        Batch {
+            controller,
+            ops: Vec::new(),
+            answers: Vec::new(),
+        }
+    }
+
+    // send an operation right away and keep track of its answer
+    fn push(
+        mut self,
+        start: impl FnOnce(&mut Handler, Done) -> Operation<dyn FnMut(bool)>,
+    ) -> Self {
+        let success = Rc::new(RefCell::new(false));
+        let success_ref = success.clone();
+        let op = start(
+            &mut self.controller.handler.borrow_handler(),
+            Some(Box::new(move |res| {
+                success_ref.borrow_mut().clone_from(&res)
+            })),
+        );
+        self.ops.push(op);
+        self.answers.push(success);
+        self
+    }
+
+    pub fn set_default_device(self, name: &str) -> Self {
+        self.push(|handler, done| {
+            D::set_default_device(&mut handler.context.borrow_mut(), name, done.unwrap())
+        })
+    }
+
+    pub fn set_device_volume_by_index(self, index: u32, volume: &ChannelVolumes) -> Self {
+        self.push(|handler, done| {
+            D::set_device_volume_by_index(&mut handler.introspect, index, volume, done)
+        })
+    }
+
+    pub fn set_device_volume_by_name(self, name: &str, volume: &ChannelVolumes) -> Self {
+        self.push(|handler, done| {
+            D::set_device_volume_by_name(&mut handler.introspect, name, volume, done)
+        })
+    }
+
+    pub fn set_device_mute_by_index(self, index: u32, mute: bool) -> Self {
+        self.push(|handler, done| {
+            D::set_device_mute_by_index(&mut handler.introspect, index, mute, done)
+        })
+    }
+
+    pub fn set_device_mute_by_name(self, name: &str, mute: bool) -> Self {
+        self.push(|handler, done| {
+            D::set_device_mute_by_name(&mut handler.introspect, name, mute, done)
+        })
+    }
+
+    pub fn set_device_port_by_index(self, index: u32, port: &str) -> Self {
+        self.push(|handler, done| {
+            D::set_device_port_by_index(&mut handler.introspect, index, port, done)
+        })
+    }
+
+    pub fn set_device_port_by_name(self, name: &str, port: &str) -> Self {
+        self.push(|handler, done| {
+            D::set_device_port_by_name(&mut handler.introspect, name, port, done)
+        })
+    }
+
+    pub fn set_app_volume(self, index: u32, volume: &ChannelVolumes) -> Self {
+        self.push(|handler, done| D::set_app_volume(&mut handler.introspect, index, volume, done))
+    }
+
+    pub fn set_app_mute(self, index: u32, mute: bool) -> Self {
+        self.push(|handler, done| D::set_app_mute(&mut handler.introspect, index, mute, done))
+    }
+
+    pub fn move_app_by_index(self, stream_index: u32, device_index: u32) -> Self {
+        self.push(|handler, done| {
+            D::move_app_by_index(&mut handler.introspect, stream_index, device_index, done)
+        })
+    }
+
+    pub fn move_app_by_name(self, stream_index: u32, device_name: &str) -> Self {
+        self.push(|handler, done| {
+            D::move_app_by_name(&mut handler.introspect, stream_index, device_name, done)
+        })
+    }
+
+    /// Number of queued changes
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Wait for the server to answer every queued change. The results are in the order the
+    /// changes were queued, `Ok(false)` when the server refused one, e.g. for a missing index
+    pub fn run(self) -> Vec<Result<bool, ControllerError>> {
+        let _span = trace::operation("batch", None, None);
+        debug!(operations = self.ops.len(), "waiting for batch");
+        let results = self
+            .controller
+            .handler
+            .borrow_handler()
+            .wait_for_operations(self.ops);
+        results
+            .into_iter()
+            .zip(self.answers)
+            .map(|(result, success)| {
+                result?;
+                let answer = *success.borrow();
+                Ok(answer)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,227 @@
+/// What the Bluetooth modules put in the property lists, interpreted
+///
+/// Headsets show up as a card whose profiles choose between high quality playback (A2DP) and
+/// the headset profiles (HSP/HFP), which also carry the microphone at telephone quality.
+/// `BluetoothInfo` gathers the address, codec, battery level and those profiles in one place,
+/// and `CardController::switch_to_headset_mode` and `switch_to_hifi_mode` flip between them.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::CardController;
+///
+/// fn main() {
+///     let mut cards = CardController::create();
+///     for card in cards.list_cards().unwrap() {
+///         if let Some(bluetooth) = card.bluetooth() {
+///             println!("{:?} battery {:?}", bluetooth.address, bluetooth.battery);
+///         }
+///     }
+///     cards.switch_to_headset_mode(1).unwrap();
+/// }
+/// ```
+use pulse::proplist::{properties, Proplist};
+
+use super::errors::{ControllerError, ControllerErrorType::*};
+use super::types::{CardInfo, CardProfileInfo, DeviceInfo};
+use super::{CardController, DeviceControl, DeviceController, Playback};
+use crate::HandlerRef;
+
+// PulseAudio puts the address in `device.string` and the codec and battery under `bluetooth.`,
+// PipeWire uses its own `api.bluez5.` keys
+const BLUEZ5_ADDRESS: &str = "api.bluez5.address";
+const BLUEZ5_CODEC: &str = "api.bluez5.codec";
+const BLUETOOTH_CODEC: &str = "bluetooth.codec";
+const BLUETOOTH_BATTERY: &str = "bluetooth.battery";
+
+/// What a card profile is for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProfileKind {
+    /// A2DP, high quality playback without a microphone.
+    HiFi,
+    /// HSP or HFP, playback and microphone at telephone quality.
+    Headset,
+    /// The card is switched off.
+    Off,
+    Other,
+}
+
+impl ProfileKind {
+    /// Tell from a profile name such as `a2dp_sink`, `a2dp-sink-aac` or `headset_head_unit`
+    pub fn from_profile_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if name == "off" {
+            ProfileKind::Off
+        } else if name.contains("a2dp") {
+            ProfileKind::HiFi
+        } else if ["headset", "handsfree", "hsp", "hfp"]
+            .iter()
+            .any(|kind| name.contains(kind))
+        {
+            ProfileKind::Headset
+        } else {
+            ProfileKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BluetoothProfile {
+    pub kind: ProfileKind,
+    pub info: CardProfileInfo,
+}
+
+impl From<&CardProfileInfo> for BluetoothProfile {
+    fn from(info: &CardProfileInfo) -> Self {
+        BluetoothProfile {
+            kind: ProfileKind::from_profile_name(info.name.as_deref().unwrap_or_default()),
+            info: info.clone(),
+        }
+    }
+}
+
+/// The Bluetooth side of a card, see `CardInfo::bluetooth`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BluetoothInfo {
+    /// Index of the card.
+    pub card: u32,
+    /// Address of the remote device, e.g. `00:1B:66:AA:BB:CC`.
+    pub address: Option<String>,
+    /// Codec in use, e.g. `sbc`, `aac` or `msbc`, when the server reports it.
+    pub codec: Option<String>,
+    /// Battery level in percent, when the device reports one between 0 and 100.
+    pub battery: Option<u8>,
+    pub profiles: Vec<BluetoothProfile>,
+    pub active_profile: Option<BluetoothProfile>,
+}
+
+impl BluetoothInfo {
+    /// What the card is currently used for
+    pub fn mode(&self) -> Option<ProfileKind> {
+        self.active_profile.as_ref().map(|profile| profile.kind)
+    }
+
+    /// The available profile of `kind` that is the best choice, preferring profiles that
+    /// provide a sink and then the server's own priority
+    pub fn best_profile(&self, kind: ProfileKind) -> Option<&BluetoothProfile> {
+        self.profiles
+            .iter()
+            .filter(|profile| profile.kind == kind && profile.info.available)
+            .max_by_key(|profile| (profile.info.n_sinks > 0, profile.info.priority))
+    }
+}
+
+impl CardInfo {
+    /// The Bluetooth view of this card, `None` when it is not a Bluetooth device. The codec is
+    /// only known here if the server sets it on the card, `CardController::get_bluetooth_info`
+    /// also looks at the card's devices
+    pub fn bluetooth(&self) -> Option<BluetoothInfo> {
+        if !is_bluetooth(&self.proplist) {
+            return None;
+        }
+        Some(BluetoothInfo {
+            card: self.index,
+            address: address(&self.proplist),
+            codec: codec(&self.proplist),
+            battery: self
+                .proplist
+                .get_str(BLUETOOTH_BATTERY)
+                .and_then(|level| level.trim_end_matches('%').trim().parse().ok())
+                .filter(|level| *level <= 100),
+            profiles: self.profiles.iter().map(From::from).collect(),
+            active_profile: self.active_profile.as_ref().map(From::from),
+        })
+    }
+}
+
+impl DeviceInfo {
+    pub fn is_bluetooth(&self) -> bool {
+        is_bluetooth(&self.proplist)
+    }
+
+    /// Codec the device is using, when the server reports it
+    pub fn bluetooth_codec(&self) -> Option<String> {
+        codec(&self.proplist)
+    }
+}
+
+impl<H: HandlerRef> CardController<H> {
+    /// The Bluetooth view of card `index`, with the codec taken from its sink or source when
+    /// the card itself doesn't say
+    pub fn get_bluetooth_info(&mut self, index: u32) -> Result<BluetoothInfo, ControllerError> {
+        let mut info = self.bluetooth_card(index)?;
+        if info.codec.is_none() {
+            let mut sinks = DeviceController::<Playback, _>::from_handler(&mut self.handler);
+            info.codec = sinks
+                .list_devices()?
+                .iter()
+                .chain(sinks.capture().list_devices()?.iter())
+                .filter(|device| device.card == Some(index))
+                .find_map(|device| device.bluetooth_codec());
+        }
+        Ok(info)
+    }
+
+    /// Put Bluetooth card `index` in a headset profile so its microphone can be used,
+    /// returns the name of the profile
+    pub fn switch_to_headset_mode(&mut self, index: u32) -> Result<String, ControllerError> {
+        self.switch_mode(index, ProfileKind::Headset)
+    }
+
+    /// Put Bluetooth card `index` in its best A2DP profile, returns the name of the profile
+    pub fn switch_to_hifi_mode(&mut self, index: u32) -> Result<String, ControllerError> {
+        self.switch_mode(index, ProfileKind::HiFi)
+    }
+
+    fn switch_mode(&mut self, index: u32, kind: ProfileKind) -> Result<String, ControllerError> {
+        let info = self.bluetooth_card(index)?;
+        if let Some(active) = info.active_profile.as_ref().filter(|p| p.kind == kind) {
+            return Ok(active.info.name.clone().unwrap_or_default());
+        }
+        let name = match info.best_profile(kind) {
+            Some(profile) => profile.info.name.clone().unwrap_or_default(),
+            None => {
+                return Err(ControllerError::new(
+                    GetInfoError,
+                    &format!("Card {} has no available {:?} profile", index, kind),
+                ))
+            }
+        };
+        if !self.set_card_profile_by_index(index, &name)? {
+            return Err(ControllerError::new(
+                PulseCtlError,
+                &format!("The server refused to switch card {} to {}", index, name),
+            ));
+        }
+        Ok(name)
+    }
+
+    fn bluetooth_card(&mut self, index: u32) -> Result<BluetoothInfo, ControllerError> {
+        self.get_card_by_index(index)?.bluetooth().ok_or_else(|| {
+            ControllerError::new(
+                GetInfoError,
+                &format!("Card {} is not a Bluetooth device", index),
+            )
+        })
+    }
+}
+
+fn is_bluetooth(proplist: &Proplist) -> bool {
+    proplist.get_str(properties::DEVICE_BUS).as_deref() == Some("bluetooth")
+        || proplist.contains(BLUEZ5_ADDRESS) == Some(true)
+}
+
+/// Address of a Bluetooth device from its property list
+pub(crate) fn address(proplist: &Proplist) -> Option<String> {
+    proplist.get_str(BLUEZ5_ADDRESS).or_else(|| {
+        match proplist.get_str(properties::DEVICE_BUS).as_deref() {
+            Some("bluetooth") => proplist.get_str(properties::DEVICE_STRING),
+            _ => None,
+        }
+    })
+}
+
+fn codec(proplist: &Proplist) -> Option<String> {
+    proplist
+        .get_str(BLUETOOTH_CODEC)
+        .or_else(|| proplist.get_str(BLUEZ5_CODEC))
+}
@@ -0,0 +1,432 @@
+use pulse::{
+    callbacks::ListResult,
+    context::{
+        introspect::{self, Introspector},
+        subscribe::Facility,
+        Context,
+    },
+    operation::Operation,
+    volume::ChannelVolumes,
+};
+
+use super::types::{ApplicationInfo, DeviceInfo, ServerInfo};
+
+/// Which side of the server a `DeviceController` works on
+///
+/// `Playback` covers sinks and the sink inputs playing to them, `Capture` covers sources and
+/// the source outputs recording from them. The trait is sealed, these two are the only
+/// directions there are.
+pub trait Direction: sealed::Ops {}
+
+/// Sinks and sink inputs, see `SinkController`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playback;
+
+/// Sources and source outputs, see `SourceController`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture;
+
+impl Direction for Playback {}
+impl Direction for Capture {}
+
+// completion callback of the introspect calls that only report success
+pub(crate) type Done = Option<Box<dyn FnMut(bool) + 'static>>;
+
+pub(crate) mod sealed {
+    use super::*;
+
+    /// Names of the introspect calls behind each operation, used to label diagnostics
+    pub struct Calls {
+        pub set_default_device: &'static str,
+        pub list_devices: &'static str,
+        pub get_device_by_index: &'static str,
+        pub get_device_by_name: &'static str,
+        pub set_device_volume_by_index: &'static str,
+        pub set_device_volume_by_name: &'static str,
+        pub set_device_mute_by_index: &'static str,
+        pub set_device_mute_by_name: &'static str,
+        pub set_device_port_by_index: &'static str,
+        pub set_device_port_by_name: &'static str,
+        pub list_applications: &'static str,
+        pub get_app_by_index: &'static str,
+        pub set_app_volume: &'static str,
+        pub set_app_mute: &'static str,
+        pub move_app_by_index: &'static str,
+        pub move_app_by_name: &'static str,
+    }
+
+    /// The per-direction half of a `DeviceController`, mapping each operation onto the
+    /// introspect call for sinks or for sources and converting what it reports
+    pub trait Ops {
+        type DeviceQuery: ?Sized;
+        type AppQuery: ?Sized;
+
+        const CLIENT_NAME: &'static str;
+        const CALLS: Calls;
+        /// Facility of the devices
+        const DEVICE: Facility;
+        /// Facility of the applications' streams
+        const APP: Facility;
+
+        fn default_device_name(info: &ServerInfo) -> Option<String>;
+
+        fn set_default_device(
+            context: &mut Context,
+            name: &str,
+            done: Box<dyn FnMut(bool)>,
+        ) -> Operation<dyn FnMut(bool)>;
+
+        fn list_devices(
+            introspect: &Introspector,
+            each: Box<dyn FnMut(DeviceInfo)>,
+        ) -> Operation<Self::DeviceQuery>;
+        fn get_device_by_index(
+            introspect: &Introspector,
+            index: u32,
+            each: Box<dyn FnMut(DeviceInfo)>,
+        ) -> Operation<Self::DeviceQuery>;
+        fn get_device_by_name(
+            introspect: &Introspector,
+            name: &str,
+            each: Box<dyn FnMut(DeviceInfo)>,
+        ) -> Operation<Self::DeviceQuery>;
+        fn set_device_volume_by_index(
+            introspect: &mut Introspector,
+            index: u32,
+            volume: &ChannelVolumes,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn set_device_volume_by_name(
+            introspect: &mut Introspector,
+            name: &str,
+            volume: &ChannelVolumes,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn set_device_mute_by_index(
+            introspect: &mut Introspector,
+            index: u32,
+            mute: bool,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn set_device_mute_by_name(
+            introspect: &mut Introspector,
+            name: &str,
+            mute: bool,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn set_device_port_by_index(
+            introspect: &mut Introspector,
+            index: u32,
+            port: &str,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn set_device_port_by_name(
+            introspect: &mut Introspector,
+            name: &str,
+            port: &str,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+
+        fn list_applications(
+            introspect: &Introspector,
+            each: Box<dyn FnMut(ApplicationInfo)>,
+        ) -> Operation<Self::AppQuery>;
+        fn get_app_by_index(
+            introspect: &Introspector,
+            index: u32,
+            each: Box<dyn FnMut(ApplicationInfo)>,
+        ) -> Operation<Self::AppQuery>;
+        fn set_app_volume(
+            introspect: &mut Introspector,
+            index: u32,
+            volume: &ChannelVolumes,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn set_app_mute(
+            introspect: &mut Introspector,
+            index: u32,
+            mute: bool,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn move_app_by_index(
+            introspect: &mut Introspector,
+            stream_index: u32,
+            device_index: u32,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+        fn move_app_by_name(
+            introspect: &mut Introspector,
+            stream_index: u32,
+            device_name: &str,
+            done: Done,
+        ) -> Operation<dyn FnMut(bool)>;
+    }
+}
+
+// The two directions only differ in which introspect calls they make and which info types
+// come back, so both impls are stamped out from the same template.
+macro_rules! direction {
+    (
+        $direction:ident {
+            client: $client:expr,
+            default_device: $default_device:ident,
+            device: $device:ident,
+            app: $app:ident,
+            device_info: $device_info:ident,
+            app_info: $app_info:ident,
+            set_default_device: $set_default_device:ident,
+            list_devices: $list_devices:ident,
+            get_device_by_index: $get_device_by_index:ident,
+            get_device_by_name: $get_device_by_name:ident,
+            set_device_volume_by_index: $set_device_volume_by_index:ident,
+            set_device_volume_by_name: $set_device_volume_by_name:ident,
+            set_device_mute_by_index: $set_device_mute_by_index:ident,
+            set_device_mute_by_name: $set_device_mute_by_name:ident,
+            set_device_port_by_index: $set_device_port_by_index:ident,
+            set_device_port_by_name: $set_device_port_by_name:ident,
+            list_applications: $list_applications:ident,
+            get_app_by_index: $get_app_by_index:ident,
+            set_app_volume: $set_app_volume:ident,
+            set_app_mute: $set_app_mute:ident,
+            move_app_by_index: $move_app_by_index:ident,
+            move_app_by_name: $move_app_by_name:ident,
+        }
+    ) => {
+        impl sealed::Ops for $direction {
+            type DeviceQuery = dyn FnMut(ListResult<&introspect::$device_info>);
+            type AppQuery = dyn FnMut(ListResult<&introspect::$app_info>);
+
+            const CLIENT_NAME: &'static str = $client;
+            const DEVICE: Facility = Facility::$device;
+            const APP: Facility = Facility::$app;
+            const CALLS: sealed::Calls = sealed::Calls {
+                set_default_device: stringify!($set_default_device),
+                list_devices: stringify!($list_devices),
+                get_device_by_index: stringify!($get_device_by_index),
+                get_device_by_name: stringify!($get_device_by_name),
+                set_device_volume_by_index: stringify!($set_device_volume_by_index),
+                set_device_volume_by_name: stringify!($set_device_volume_by_name),
+                set_device_mute_by_index: stringify!($set_device_mute_by_index),
+                set_device_mute_by_name: stringify!($set_device_mute_by_name),
+                set_device_port_by_index: stringify!($set_device_port_by_index),
+                set_device_port_by_name: stringify!($set_device_port_by_name),
+                list_applications: stringify!($list_applications),
+                get_app_by_index: stringify!($get_app_by_index),
+                set_app_volume: stringify!($set_app_volume),
+                set_app_mute: stringify!($set_app_mute),
+                move_app_by_index: stringify!($move_app_by_index),
+                move_app_by_name: stringify!($move_app_by_name),
+            };
+
+            fn default_device_name(info: &ServerInfo) -> Option<String> {
+                info.$default_device.clone()
+            }
+
+            fn set_default_device(
+                context: &mut Context,
+                name: &str,
+                done: Box<dyn FnMut(bool)>,
+            ) -> Operation<dyn FnMut(bool)> {
+                context.$set_default_device(name, done)
+            }
+
+            fn list_devices(
+                introspect: &Introspector,
+                mut each: Box<dyn FnMut(DeviceInfo)>,
+            ) -> Operation<Self::DeviceQuery> {
+                introspect.$list_devices(move |res: ListResult<&introspect::$device_info>| {
+                    if let ListResult::Item(item) = res {
+                        each(item.into());
+                    }
+                })
+            }
+            fn get_device_by_index(
+                introspect: &Introspector,
+                index: u32,
+                mut each: Box<dyn FnMut(DeviceInfo)>,
+            ) -> Operation<Self::DeviceQuery> {
+                introspect.$get_device_by_index(
+                    index,
+                    move |res: ListResult<&introspect::$device_info>| {
+                        if let ListResult::Item(item) = res {
+                            each(item.into());
+                        }
+                    },
+                )
+            }
+            fn get_device_by_name(
+                introspect: &Introspector,
+                name: &str,
+                mut each: Box<dyn FnMut(DeviceInfo)>,
+            ) -> Operation<Self::DeviceQuery> {
+                introspect.$get_device_by_name(
+                    name,
+                    move |res: ListResult<&introspect::$device_info>| {
+                        if let ListResult::Item(item) = res {
+                            each(item.into());
+                        }
+                    },
+                )
+            }
+            fn set_device_volume_by_index(
+                introspect: &mut Introspector,
+                index: u32,
+                volume: &ChannelVolumes,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_device_volume_by_index(index, volume, done)
+            }
+            fn set_device_volume_by_name(
+                introspect: &mut Introspector,
+                name: &str,
+                volume: &ChannelVolumes,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_device_volume_by_name(name, volume, done)
+            }
+            fn set_device_mute_by_index(
+                introspect: &mut Introspector,
+                index: u32,
+                mute: bool,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_device_mute_by_index(index, mute, done)
+            }
+            fn set_device_mute_by_name(
+                introspect: &mut Introspector,
+                name: &str,
+                mute: bool,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_device_mute_by_name(name, mute, done)
+            }
+            fn set_device_port_by_index(
+                introspect: &mut Introspector,
+                index: u32,
+                port: &str,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_device_port_by_index(index, port, done)
+            }
+            fn set_device_port_by_name(
+                introspect: &mut Introspector,
+                name: &str,
+                port: &str,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_device_port_by_name(name, port, done)
+            }
+
+            fn list_applications(
+                introspect: &Introspector,
+                mut each: Box<dyn FnMut(ApplicationInfo)>,
+            ) -> Operation<Self::AppQuery> {
+                introspect.$list_applications(move |res: ListResult<&introspect::$app_info>| {
+                    if let ListResult::Item(item) = res {
+                        each(item.into());
+                    }
+                })
+            }
+            fn get_app_by_index(
+                introspect: &Introspector,
+                index: u32,
+                mut each: Box<dyn FnMut(ApplicationInfo)>,
+            ) -> Operation<Self::AppQuery> {
+                introspect.$get_app_by_index(
+                    index,
+                    move |res: ListResult<&introspect::$app_info>| {
+                        if let ListResult::Item(item) = res {
+                            each(item.into());
+                        }
+                    },
+                )
+            }
+            fn set_app_volume(
+                introspect: &mut Introspector,
+                index: u32,
+                volume: &ChannelVolumes,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_app_volume(index, volume, done)
+            }
+            fn set_app_mute(
+                introspect: &mut Introspector,
+                index: u32,
+                mute: bool,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$set_app_mute(index, mute, done)
+            }
+            fn move_app_by_index(
+                introspect: &mut Introspector,
+                stream_index: u32,
+                device_index: u32,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$move_app_by_index(stream_index, device_index, done)
+            }
+            fn move_app_by_name(
+                introspect: &mut Introspector,
+                stream_index: u32,
+                device_name: &str,
+                done: Done,
+            ) -> Operation<dyn FnMut(bool)> {
+                introspect.$move_app_by_name(stream_index, device_name, done)
+            }
+        }
+    };
+}
+
+direction! {
+    Playback {
+        client: "SinkController",
+        default_device: default_sink_name,
+        device: Sink,
+        app: SinkInput,
+        device_info: SinkInfo,
+        app_info: SinkInputInfo,
+        set_default_device: set_default_sink,
+        list_devices: get_sink_info_list,
+        get_device_by_index: get_sink_info_by_index,
+        get_device_by_name: get_sink_info_by_name,
+        set_device_volume_by_index: set_sink_volume_by_index,
+        set_device_volume_by_name: set_sink_volume_by_name,
+        set_device_mute_by_index: set_sink_mute_by_index,
+        set_device_mute_by_name: set_sink_mute_by_name,
+        set_device_port_by_index: set_sink_port_by_index,
+        set_device_port_by_name: set_sink_port_by_name,
+        list_applications: get_sink_input_info_list,
+        get_app_by_index: get_sink_input_info,
+        set_app_volume: set_sink_input_volume,
+        set_app_mute: set_sink_input_mute,
+        move_app_by_index: move_sink_input_by_index,
+        move_app_by_name: move_sink_input_by_name,
+    }
+}
+
+direction! {
+    Capture {
+        client: "SourceController",
+        default_device: default_source_name,
+        device: Source,
+        app: SourceOutput,
+        device_info: SourceInfo,
+        app_info: SourceOutputInfo,
+        set_default_device: set_default_source,
+        list_devices: get_source_info_list,
+        get_device_by_index: get_source_info_by_index,
+        get_device_by_name: get_source_info_by_name,
+        set_device_volume_by_index: set_source_volume_by_index,
+        set_device_volume_by_name: set_source_volume_by_name,
+        set_device_mute_by_index: set_source_mute_by_index,
+        set_device_mute_by_name: set_source_mute_by_name,
+        set_device_port_by_index: set_source_port_by_index,
+        set_device_port_by_name: set_source_port_by_name,
+        list_applications: get_source_output_info_list,
+        get_app_by_index: get_source_output_info,
+        set_app_volume: set_source_output_volume,
+        set_app_mute: set_source_output_mute,
+        move_app_by_index: move_source_output_by_index,
+        move_app_by_name: move_source_output_by_name,
+    }
+}
@@ -0,0 +1,218 @@
+/// Processed microphones through `module-echo-cancel`
+///
+/// The module puts a virtual source in front of a microphone and a virtual sink in front of the
+/// speakers it hears. Audio played to the sink is subtracted from what the microphone picks up,
+/// and the `webrtc` method can also suppress noise and even out the level. Applications record
+/// from the new source and play to the new sink, `make_default` arranges that for all of them.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::echo_cancel::{AecMethod, EchoCancelBuilder};
+/// use pulsectl::PulseCtl;
+///
+/// fn main() {
+///     let pulse = PulseCtl::connect("conference").unwrap();
+///     let echo_cancel = EchoCancelBuilder::new()
+///         .source("alsa_input.usb-headset.mono-fallback")
+///         .sink("alsa_output.usb-headset.analog-stereo")
+///         .method(AecMethod::WebRtc)
+///         .noise_suppression(true)
+///         .make_default(true)
+///         .load(&mut pulse.modules())
+///         .unwrap();
+///     println!("recording from {:?}", echo_cancel.source.name);
+/// }
+/// ```
+use super::errors::{ControllerError, ControllerErrorType::*};
+use super::types::DeviceInfo;
+use super::virtual_devices::{arg, escape, VirtualDevice};
+use super::{Capture, DeviceControl, DeviceController, ModuleController};
+use crate::SharedHandler;
+
+/// Algorithm the module cancels echo with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AecMethod {
+    /// WebRTC's audio processing, also does noise suppression and gain control.
+    WebRtc,
+    Speex,
+    /// Passes audio through untouched, for testing a setup.
+    Null,
+}
+
+impl AecMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            AecMethod::WebRtc => "webrtc",
+            AecMethod::Speex => "speex",
+            AecMethod::Null => "null",
+        }
+    }
+}
+
+/// Settings for loading `module-echo-cancel`, devices not given are the server's defaults
+pub struct EchoCancelBuilder {
+    source: Option<String>,
+    sink: Option<String>,
+    method: AecMethod,
+    aec_args: Vec<(String, String)>,
+    source_name: Option<String>,
+    sink_name: Option<String>,
+    make_default: bool,
+}
+
+/// The devices created by `EchoCancelBuilder::load`, removed when `device` is dropped
+pub struct EchoCancel {
+    pub device: VirtualDevice,
+    /// The processed microphone.
+    pub source: DeviceInfo,
+    /// The sink whose audio is cancelled from the microphone.
+    pub sink: DeviceInfo,
+}
+
+impl Default for EchoCancelBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EchoCancelBuilder {
+    pub fn new() -> Self {
+        EchoCancelBuilder {
+            source: None,
+            sink: None,
+            method: AecMethod::WebRtc,
+            aec_args: Vec::new(),
+            source_name: None,
+            sink_name: None,
+            make_default: false,
+        }
+    }
+
+    /// The microphone to process
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// The speakers the microphone hears
+    pub fn sink(mut self, sink: &str) -> Self {
+        self.sink = Some(sink.to_string());
+        self
+    }
+
+    /// `AecMethod::WebRtc` unless set
+    pub fn method(mut self, method: AecMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// An option of the method, e.g. `analog_gain_control` = `0` for `webrtc`
+    pub fn aec_arg(mut self, key: &str, value: &str) -> Self {
+        self.aec_args.retain(|(k, _)| k != key);
+        self.aec_args.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Whether `webrtc` suppresses background noise as well
+    pub fn noise_suppression(self, enabled: bool) -> Self {
+        self.aec_arg("noise_suppression", if enabled { "1" } else { "0" })
+    }
+
+    /// Name of the processed source
+    pub fn source_name(mut self, name: &str) -> Self {
+        self.source_name = Some(name.to_string());
+        self
+    }
+
+    /// Name of the sink in front of the speakers
+    pub fn sink_name(mut self, name: &str) -> Self {
+        self.sink_name = Some(name.to_string());
+        self
+    }
+
+    /// Make the new source and sink the defaults once they exist
+    pub fn make_default(mut self, make_default: bool) -> Self {
+        self.make_default = make_default;
+        self
+    }
+
+    /// The argument passed to the module
+    pub fn argument(&self) -> String {
+        let mut args = vec![arg("aec_method", self.method.as_str())];
+        let options = [
+            ("source_master", &self.source),
+            ("sink_master", &self.sink),
+            ("source_name", &self.source_name),
+            ("sink_name", &self.sink_name),
+        ];
+        for (key, value) in options.iter() {
+            if let Some(value) = value {
+                args.push(arg(key, value));
+            }
+        }
+        if !self.aec_args.is_empty() {
+            let aec_args: Vec<String> = self
+                .aec_args
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value, '"')))
+                .collect();
+            args.push(arg("aec_args", &aec_args.join(" ")));
+        }
+        args.join(" ")
+    }
+
+    /// Load the module, with `make_default` the load fails and the module is unloaded again
+    /// when the server refuses to make the new devices the defaults
+    pub fn load(
+        self,
+        modules: &mut ModuleController<SharedHandler>,
+    ) -> Result<EchoCancel, ControllerError> {
+        let mut device = modules.load_virtual_device("module-echo-cancel", &self.argument())?;
+        let source = device.source()?;
+        let sink = device.sink()?;
+        if self.make_default {
+            if let Err(error) = make_default(modules.handler.clone(), &source, &sink) {
+                device.destroy()?;
+                return Err(error);
+            }
+        }
+        Ok(EchoCancel {
+            device,
+            source,
+            sink,
+        })
+    }
+}
+
+fn make_default(
+    handler: SharedHandler,
+    source: &DeviceInfo,
+    sink: &DeviceInfo,
+) -> Result<(), ControllerError> {
+    let mut sources = DeviceController::<Capture, _>::from_handler(handler);
+    let name = device_name(source)?;
+    if !sources.set_default_device(name)? {
+        return Err(refused(name));
+    }
+    let name = device_name(sink)?;
+    if !sources.playback().set_default_device(name)? {
+        return Err(refused(name));
+    }
+    Ok(())
+}
+
+fn refused(name: &str) -> ControllerError {
+    ControllerError::new(
+        PulseCtlError,
+        &format!("The server refused to make {} the default", name),
+    )
+}
+
+fn device_name(device: &DeviceInfo) -> Result<&str, ControllerError> {
+    device.name.as_deref().ok_or_else(|| {
+        ControllerError::new(
+            GetInfoError,
+            "module-echo-cancel created a device without a name",
+        )
+    })
+}
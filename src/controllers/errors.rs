@@ -7,7 +7,11 @@ use crate::PulseCtlError;
 impl From<PulseCtlError> for ControllerError {
     fn from(error: super::errors::PulseCtlError) -> Self {
         ControllerError {
-            error: ControllerErrorType::PulseCtlError,
+            error: if error.is_timeout() {
+                ControllerErrorType::TimeoutError
+            } else {
+                ControllerErrorType::PulseCtlError
+            },
             message: format!("{:?}", error),
         }
     }
@@ -23,19 +27,30 @@ impl fmt::Debug for ControllerError {
             ControllerErrorType::GetInfoError => {
                 error_string.push_str("GetInfoError");
             }
+            ControllerErrorType::TimeoutError => {
+                error_string.push_str("TimeoutError");
+            }
+            ControllerErrorType::UnsupportedError => {
+                error_string.push_str("UnsupportedError");
+            }
         }
         write!(f, "[{}]: {}", error_string, self.message)
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum ControllerErrorType {
     PulseCtlError,
     GetInfoError,
+    TimeoutError,
+    UnsupportedError,
 }
 
 /// Error thrown while fetching data from pulseaudio,
-/// has two variants: PulseCtlError for when PulseAudio returns an error code
-/// and GetInfoError when a request for data fails for whatever reason
+/// has four variants: PulseCtlError for when PulseAudio returns an error code,
+/// GetInfoError when a request for data fails for whatever reason,
+/// TimeoutError when the server did not answer in time
+/// and UnsupportedError when a controller doesn't implement the requested method
 pub struct ControllerError {
     error: ControllerErrorType,
     message: String,
@@ -48,4 +63,14 @@ impl ControllerError {
             message: msg.to_string(),
         }
     }
+
+    /// Whether the request failed because the server did not answer in time
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.error, ControllerErrorType::TimeoutError)
+    }
+
+    // the server answered, but had nothing under the requested index or name
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(self.error, ControllerErrorType::GetInfoError)
+    }
 }
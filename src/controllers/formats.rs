@@ -0,0 +1,167 @@
+/// Choosing the encodings a sink accepts, for passing compressed audio to a receiver
+///
+/// HDMI and S/PDIF sinks can carry AC3, EAC3, DTS and, with the `pa_v13` feature, TrueHD
+/// untouched to an amplifier that decodes it. The server only offers those encodings to
+/// applications once the sink has been told the receiver understands them, which is what
+/// `set_sink_formats` and `enable_passthrough` do. The choice is stored by
+/// `module-device-restore`, which the default server configuration loads.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::formats::Passthrough;
+/// use pulsectl::controllers::{DeviceControl, SinkController};
+///
+/// fn main() {
+///     let mut sinks = SinkController::create();
+///     let hdmi = sinks.get_device_by_name("alsa_output.pci-0000_01_00.1.hdmi-stereo").unwrap();
+///     sinks
+///         .enable_passthrough(hdmi.index, &[Passthrough::Ac3, Passthrough::Dts])
+///         .unwrap();
+/// }
+/// ```
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pulse::{
+    def,
+    format::{self, Encoding},
+};
+
+use super::errors::ControllerError;
+use super::types::{ApplicationInfo, DeviceInfo};
+use super::{DeviceControl, DeviceController, Playback};
+use crate::trace;
+use crate::HandlerRef;
+
+/// Compressed encodings a sink can pass through to a receiver
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Passthrough {
+    /// Dolby Digital.
+    Ac3,
+    /// Dolby Digital Plus.
+    Eac3,
+    Dts,
+    /// Dolby TrueHD, needs a server of version 13 or later.
+    #[cfg(feature = "pa_v13")]
+    TrueHd,
+}
+
+impl Passthrough {
+    /// Every encoding this build knows about
+    pub fn all() -> Vec<Passthrough> {
+        vec![
+            Passthrough::Ac3,
+            Passthrough::Eac3,
+            Passthrough::Dts,
+            #[cfg(feature = "pa_v13")]
+            Passthrough::TrueHd,
+        ]
+    }
+
+    pub fn encoding(self) -> Encoding {
+        match self {
+            Passthrough::Ac3 => Encoding::AC3_IEC61937,
+            Passthrough::Eac3 => Encoding::EAC3_IEC61937,
+            Passthrough::Dts => Encoding::DTS_IEC61937,
+            #[cfg(feature = "pa_v13")]
+            Passthrough::TrueHd => Encoding::TRUEHD_IEC61937,
+        }
+    }
+
+    pub fn from_encoding(encoding: Encoding) -> Option<Self> {
+        Passthrough::all()
+            .into_iter()
+            .find(|passthrough| passthrough.encoding() == encoding)
+    }
+
+    /// A format carrying this encoding, as passed to `set_sink_formats`
+    pub fn format(self) -> format::Info {
+        encoding_format(self.encoding())
+    }
+}
+
+impl DeviceInfo {
+    /// Whether the device currently accepts `passthrough`
+    pub fn supports_passthrough(&self, passthrough: Passthrough) -> bool {
+        self.formats
+            .iter()
+            .any(|format| format.get_encoding() == passthrough.encoding())
+    }
+
+    /// The compressed encodings the device currently accepts
+    pub fn passthrough_formats(&self) -> Vec<Passthrough> {
+        self.formats
+            .iter()
+            .filter_map(|format| Passthrough::from_encoding(format.get_encoding()))
+            .collect()
+    }
+}
+
+impl ApplicationInfo {
+    /// The compressed encoding the stream is passing through, `None` for PCM
+    pub fn passthrough(&self) -> Option<Passthrough> {
+        Passthrough::from_encoding(self.format.get_encoding())
+    }
+}
+
+impl<H: HandlerRef> DeviceController<Playback, H> {
+    /// Replace the formats sink `index` accepts, like pavucontrol's advanced settings do.
+    /// Returns whether the sink accepts exactly the encodings of `formats` afterwards, sinks
+    /// that can't pass compressed audio keep their formats and give `false`
+    pub fn set_sink_formats(
+        &mut self,
+        index: u32,
+        formats: &[format::Info],
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation("set_sink_formats", Some(index), None);
+        let wanted: Vec<Encoding> = formats.iter().map(|f| f.get_encoding()).collect();
+        let mut formats = formats.to_vec();
+        let mut formats: Vec<&mut format::Info> = formats.iter_mut().collect();
+        let success = Rc::new(RefCell::new(false));
+        let success_ref = success.clone();
+        {
+            let mut handler = self.handler.borrow_handler();
+            let op = handler.context.borrow().device_restore().save_formats(
+                def::Device::Sink,
+                index,
+                &mut formats,
+                move |res| success_ref.borrow_mut().clone_from(&res),
+            );
+            handler.wait_for_operation(op)?;
+        }
+        if !*success.borrow() {
+            return Ok(false);
+        }
+        // module-device-restore acknowledges the request even when the sink keeps its formats
+        let accepted: Vec<Encoding> = self
+            .get_device_by_index(index)?
+            .formats
+            .iter()
+            .map(|f| f.get_encoding())
+            .collect();
+        Ok(wanted.iter().all(|e| accepted.contains(e))
+            && accepted.iter().all(|e| wanted.contains(e)))
+    }
+
+    /// Let sink `index` accept PCM and exactly the `passthrough` encodings
+    pub fn enable_passthrough(
+        &mut self,
+        index: u32,
+        passthrough: &[Passthrough],
+    ) -> Result<bool, ControllerError> {
+        let mut formats = vec![encoding_format(Encoding::PCM)];
+        formats.extend(passthrough.iter().map(|p| p.format()));
+        self.set_sink_formats(index, &formats)
+    }
+
+    /// Let sink `index` accept PCM only
+    pub fn disable_passthrough(&mut self, index: u32) -> Result<bool, ControllerError> {
+        self.enable_passthrough(index, &[])
+    }
+}
+
+fn encoding_format(encoding: Encoding) -> format::Info {
+    let mut format = format::Info::new().expect("Unable to allocate a format");
+    format.set_encoding(encoding);
+    format
+}
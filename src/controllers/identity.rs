@@ -0,0 +1,180 @@
+/// Recognising the same physical device after it was unplugged, re-paired or the server restarted
+///
+/// Indexes are handed out anew every time a device appears, and some names change along with
+/// the profile a card is in. A `DeviceId` instead remembers the properties the kernel and
+/// Bluetooth stack keep stable: the bus path, the serial number and the Bluetooth address,
+/// plus the device name as a last resort. Ids can be stored as text and parsed back later.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::identity::DeviceId;
+/// use pulsectl::controllers::{DeviceControl, SinkController};
+///
+/// fn main() {
+///     let mut sinks = SinkController::create();
+///     let saved = sinks.get_default_device().unwrap().id().to_string();
+///
+///     // ...later, possibly after a reboot
+///     let id: DeviceId = saved.parse().unwrap();
+///     let device = sinks.get_device_by_id(&id).unwrap();
+///     sinks.set_default_device(device.name.as_ref().unwrap()).unwrap();
+/// }
+/// ```
+use std::fmt;
+use std::str::FromStr;
+
+use pulse::proplist::properties;
+
+use super::bluetooth;
+use super::types::DeviceInfo;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceId {
+    /// Name of the device on the server.
+    pub name: Option<String>,
+    /// Where the device is attached, e.g. `pci-0000:00:1f.3` or `pci-0000:00:14.0-usb-0:2:1.0`.
+    pub bus_path: Option<String>,
+    /// Serial number reported by the hardware.
+    pub serial: Option<String>,
+    /// Address of a Bluetooth device.
+    pub bluetooth_address: Option<String>,
+    /// Card profile the device belongs to, which tells apart the devices one card provides.
+    pub profile: Option<String>,
+}
+
+impl DeviceId {
+    /// Whether both ids point at the same device. Hardware keys known on both sides decide,
+    /// the name is only compared when there are none
+    pub fn same_device(&self, other: &DeviceId) -> bool {
+        if let (Some(a), Some(b)) = (&self.bluetooth_address, &other.bluetooth_address) {
+            // a headset only ever shows up as one device at a time, whatever its profile
+            return a.eq_ignore_ascii_case(b);
+        }
+        let hardware = match (&self.serial, &other.serial) {
+            (Some(a), Some(b)) => Some(a == b),
+            _ => match (&self.bus_path, &other.bus_path) {
+                (Some(a), Some(b)) => Some(a == b),
+                _ => None,
+            },
+        };
+        match hardware {
+            Some(false) => false,
+            Some(true) => match (&self.profile, &other.profile) {
+                (Some(a), Some(b)) => a == b,
+                _ => self.name == other.name,
+            },
+            None => self.name.is_some() && self.name == other.name,
+        }
+    }
+
+    /// Whether `device` is the device this id was taken from
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        self.same_device(&device.id())
+    }
+}
+
+impl DeviceInfo {
+    /// The stable identity of this device, see `DeviceId`
+    pub fn id(&self) -> DeviceId {
+        DeviceId {
+            name: self.name.clone(),
+            bus_path: self.proplist.get_str(properties::DEVICE_BUS_PATH),
+            serial: self.proplist.get_str(properties::DEVICE_SERIAL),
+            bluetooth_address: bluetooth::address(&self.proplist),
+            profile: self.proplist.get_str(properties::DEVICE_PROFILE_NAME),
+        }
+    }
+}
+
+/// Written as `key=value` pairs separated by `;`, with `%`, `;`, `=`, spaces and quotes
+/// percent-encoded so the result can be dropped into a rules file as is
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = [
+            ("bluetooth", &self.bluetooth_address),
+            ("serial", &self.serial),
+            ("bus_path", &self.bus_path),
+            ("profile", &self.profile),
+            ("name", &self.name),
+        ];
+        let mut first = true;
+        for (key, value) in fields.iter() {
+            if let Some(value) = value {
+                if !first {
+                    write!(f, ";")?;
+                }
+                write!(f, "{}={}", key, escape(value))?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a stored `DeviceId` can't be parsed
+pub struct DeviceIdParseError {
+    message: String,
+}
+
+impl fmt::Debug for DeviceIdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[DeviceIdParseError]: {}", self.message)
+    }
+}
+
+impl FromStr for DeviceId {
+    type Err = DeviceIdParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = |message: String| DeviceIdParseError { message };
+        let mut id = DeviceId::default();
+        for pair in text.split(';').filter(|pair| !pair.is_empty()) {
+            let (key, value) = match pair.find('=') {
+                Some(pos) => (&pair[..pos], unescape(&pair[pos + 1..]).map_err(error)?),
+                None => return Err(error(format!("expected key=value, found '{}'", pair))),
+            };
+            let field = match key {
+                "bluetooth" => &mut id.bluetooth_address,
+                "serial" => &mut id.serial,
+                "bus_path" => &mut id.bus_path,
+                "profile" => &mut id.profile,
+                "name" => &mut id.name,
+                _ => return Err(error(format!("unknown key '{}'", key))),
+            };
+            *field = Some(value);
+        }
+        if id == DeviceId::default() {
+            return Err(error("the id is empty".to_string()));
+        }
+        Ok(id)
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '%' | ';' | '=' | ' ' | '"' | '\'' => escaped.push_str(&format!("%{:02X}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            unescaped.push(c);
+            continue;
+        }
+        let code: String = chars.by_ref().take(2).collect();
+        // from_str_radix would take a sign, as in `%+f`
+        if code.len() != 2 || !code.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid escape '%{}'", code));
+        }
+        unescaped.push(u8::from_str_radix(&code, 16).unwrap() as char);
+    }
+    Ok(unescaped)
+}
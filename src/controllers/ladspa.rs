@@ -0,0 +1,221 @@
+/// Filter sinks running a LADSPA plugin in front of a device, through `module-ladspa-sink`
+///
+/// The filter is an ordinary sink, it shows up in `list_devices` and streams are moved to it
+/// like to any other. Audio played to it goes through the plugin and on to the master device.
+/// The native protocol can't change the controls of a loaded module, so `set_controls` unloads
+/// the filter and loads it anew. Playback has an audible gap while it does, and the streams are
+/// rescued to another sink by the server before they are moved back, along with the filter's
+/// place as default.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::ladspa::LadspaPlugin;
+/// use pulsectl::PulseCtl;
+///
+/// fn main() {
+///     let pulse = PulseCtl::connect("equalizer").unwrap();
+///     // a 10 band equalizer from the CAPS plugin collection, bands in dB
+///     let eq = LadspaPlugin::new("caps", "Eq10X2")
+///         .controls(&[3.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0]);
+///     let mut filter = pulse
+///         .modules()
+///         .create_ladspa_sink("equalizer", "alsa_output.pci-0000_00_1f.3.analog-stereo", &eq)
+///         .unwrap();
+///     filter.set_control(0, 6.0).unwrap();
+///     filter.remove().unwrap();
+/// }
+/// ```
+use super::errors::{ControllerError, ControllerErrorType::*};
+use super::types::DeviceInfo;
+use super::virtual_devices::{arg, VirtualDevice};
+use super::{AppControl, DeviceControl, DeviceController, ModuleController, Playback};
+use crate::SharedHandler;
+
+/// The plugin a filter sink runs and the values of its control ports
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadspaPlugin {
+    /// Library the plugin is in, e.g. `caps` for `caps.so` on the LADSPA path.
+    pub plugin: String,
+    /// Label of the plugin inside the library.
+    pub label: String,
+    /// Values of the input control ports in order, `None` leaves a port at its default.
+    pub controls: Vec<Option<f32>>,
+}
+
+impl LadspaPlugin {
+    pub fn new(plugin: &str, label: &str) -> Self {
+        LadspaPlugin {
+            plugin: plugin.to_string(),
+            label: label.to_string(),
+            controls: Vec::new(),
+        }
+    }
+
+    /// Set the next control port
+    pub fn control(mut self, value: f32) -> Self {
+        self.controls.push(Some(value));
+        self
+    }
+
+    /// Leave the next control port at the plugin's default
+    pub fn default_control(mut self) -> Self {
+        self.controls.push(None);
+        self
+    }
+
+    /// Set the next control ports, in order
+    pub fn controls(mut self, values: &[f32]) -> Self {
+        self.controls.extend(values.iter().copied().map(Some));
+        self
+    }
+
+    /// The module argument for a filter sink called `name` in front of `master`
+    pub fn argument(&self, name: &str, master: &str) -> String {
+        let mut args = vec![
+            arg("sink_name", name),
+            arg("master", master),
+            arg("plugin", &self.plugin),
+            arg("label", &self.label),
+        ];
+        if !self.controls.is_empty() {
+            let controls: Vec<String> = self
+                .controls
+                .iter()
+                .map(|value| value.map(|v| v.to_string()).unwrap_or_default())
+                .collect();
+            args.push(arg("control", &controls.join(",")));
+        }
+        args.join(" ")
+    }
+}
+
+/// A LADSPA filter sink, removed again when dropped
+pub struct FilterSink {
+    modules: ModuleController<SharedHandler>,
+    device: VirtualDevice,
+    name: String,
+    master: String,
+    plugin: LadspaPlugin,
+}
+
+impl FilterSink {
+    /// Name of the filter sink
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name of the device the filtered audio is played on
+    pub fn master(&self) -> &str {
+        &self.master
+    }
+
+    pub fn plugin(&self) -> &LadspaPlugin {
+        &self.plugin
+    }
+
+    /// Index of the module running the filter, changes whenever the controls are set.
+    /// `None` once a reload failed so badly that the filter is gone
+    pub fn module(&self) -> Option<u32> {
+        self.device.module()
+    }
+
+    /// Current state of the filter sink
+    pub fn device(&mut self) -> Result<DeviceInfo, ControllerError> {
+        self.device.sink()
+    }
+
+    /// Set control port `port` and reload the filter, see `set_controls`
+    pub fn set_control(&mut self, port: usize, value: f32) -> Result<(), ControllerError> {
+        let mut controls = self.plugin.controls.clone();
+        if controls.len() <= port {
+            controls.resize(port + 1, None);
+        }
+        controls[port] = Some(value);
+        self.set_controls(controls)
+    }
+
+    /// Replace the values of all control ports and reload the filter, streams playing to it and
+    /// its place as default move over to the new one. When the server refuses the new controls
+    /// the filter is reloaded with the previous ones and the error returned
+    pub fn set_controls(&mut self, controls: Vec<Option<f32>>) -> Result<(), ControllerError> {
+        let mut sinks = DeviceController::<Playback, _>::from_handler(self.modules.handler.clone());
+        let old = self.device.sink()?;
+        let streams: Vec<u32> = sinks
+            .list_applications()?
+            .into_iter()
+            .filter(|app| app.connection_id == old.index)
+            .map(|app| app.index)
+            .collect();
+        let was_default = sinks.get_server_info()?.default_sink_name == old.name;
+
+        let mut plugin = self.plugin.clone();
+        plugin.controls = controls;
+        self.device.unload()?;
+        let result = match self.load(&plugin) {
+            Ok(()) => {
+                self.plugin = plugin;
+                Ok(())
+            }
+            Err(error) => {
+                self.load(&self.plugin.clone())?;
+                Err(error)
+            }
+        };
+
+        let new = self.device.sink()?;
+        if was_default {
+            sinks.set_default_device(&self.name)?;
+        }
+        let mut batch = sinks.batch();
+        for stream in streams {
+            batch = batch.move_app_by_index(stream, new.index);
+        }
+        // streams that ended in the meantime stay gone
+        for result in batch.run() {
+            result?;
+        }
+        result
+    }
+
+    /// Unload the filter now rather than when the handle is dropped
+    pub fn remove(self) -> Result<bool, ControllerError> {
+        self.device.destroy()
+    }
+
+    fn load(&mut self, plugin: &LadspaPlugin) -> Result<(), ControllerError> {
+        self.device = self.modules.load_virtual_device(
+            "module-ladspa-sink",
+            &plugin.argument(&self.name, &self.master),
+        )?;
+        Ok(())
+    }
+}
+
+impl ModuleController<SharedHandler> {
+    /// Put a filter sink called `name` running `plugin` in front of sink `master`
+    pub fn create_ladspa_sink(
+        &mut self,
+        name: &str,
+        master: &str,
+        plugin: &LadspaPlugin,
+    ) -> Result<FilterSink, ControllerError> {
+        let device =
+            self.load_virtual_device("module-ladspa-sink", &plugin.argument(name, master))?;
+        let name = match device.sink_name() {
+            Some(name) => name.to_string(),
+            None => {
+                return Err(ControllerError::new(
+                    GetInfoError,
+                    "module-ladspa-sink did not create a sink",
+                ))
+            }
+        };
+        Ok(FilterSink {
+            modules: ModuleController::from_handler(self.handler.clone()),
+            device,
+            name,
+            master: master.to_string(),
+            plugin: plugin.clone(),
+        })
+    }
+}
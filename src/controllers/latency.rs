@@ -0,0 +1,130 @@
+/// How late audio reaches the speakers, or the application, and how to correct for it
+///
+/// Every card port has a latency offset the server adds to the latency of its devices, which is
+/// how the delay of an HDMI receiver or a Bluetooth headset that the server can't measure is made
+/// known to applications. `latency_report` adds up what each stream currently waits for, so the
+/// offset can be tuned until picture and sound line up.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::{CardController, SinkController};
+///
+/// fn main() {
+///     let mut sinks = SinkController::create();
+///     for report in sinks.latency_report().unwrap() {
+///         println!("stream {}: {} usec", report.stream, report.total().0);
+///     }
+///
+///     let mut cards = CardController::create();
+///     cards
+///         .set_port_latency_offset("alsa_card.pci-0000_01_00.1", "hdmi-output-0", 120_000)
+///         .unwrap();
+/// }
+/// ```
+use pulse::time::MicroSeconds;
+
+use super::errors::{ControllerError, ControllerErrorType::*};
+use super::types::{ApplicationInfo, CardInfo, DeviceInfo};
+use super::{AppControl, CardController, DeviceControl, DeviceController, Direction};
+use crate::trace;
+use crate::HandlerRef;
+
+/// The latency of one application stream, see `DeviceController::latency_report`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyReport {
+    /// Index of the stream.
+    pub stream: u32,
+    /// Index of the device the stream is connected to.
+    pub device: u32,
+    /// Audio buffered for the stream on the server.
+    pub buffer: MicroSeconds,
+    /// Latency of the device, including its port's latency offset.
+    pub device_latency: MicroSeconds,
+    /// Latency offset of the device's active port in microseconds, `None` for devices that
+    /// don't belong to a card.
+    pub port_offset: Option<i64>,
+}
+
+impl LatencyReport {
+    /// Time between the application handing over audio and it being played, or between it
+    /// being recorded and the application receiving it
+    pub fn total(&self) -> MicroSeconds {
+        self.buffer + self.device_latency
+    }
+}
+
+impl ApplicationInfo {
+    /// Buffer and device latency of this stream added up
+    pub fn latency(&self) -> MicroSeconds {
+        self.buffer_usec + self.connection_usec
+    }
+}
+
+impl<D: Direction, H: HandlerRef> DeviceController<D, H> {
+    /// The latency of every application stream of this direction
+    pub fn latency_report(&mut self) -> Result<Vec<LatencyReport>, ControllerError> {
+        let apps = self.list_applications()?;
+        let devices = self.list_devices()?;
+        let cards = CardController::from_handler(&mut self.handler).list_cards()?;
+        Ok(apps
+            .iter()
+            .map(|app| LatencyReport {
+                stream: app.index,
+                device: app.connection_id,
+                buffer: app.buffer_usec,
+                device_latency: app.connection_usec,
+                port_offset: devices
+                    .iter()
+                    .find(|device| device.index == app.connection_id)
+                    .and_then(|device| port_offset(device, &cards)),
+            })
+            .collect())
+    }
+}
+
+impl<H: HandlerRef> CardController<H> {
+    /// Latency offset of `port` on card `card_name`, in microseconds
+    pub fn get_port_latency_offset(
+        &mut self,
+        card_name: &str,
+        port: &str,
+    ) -> Result<i64, ControllerError> {
+        let card = self.get_card_by_name(card_name)?;
+        card.ports
+            .iter()
+            .find(|p| p.name.as_deref() == Some(port))
+            .map(|p| p.latency_offset)
+            .ok_or_else(|| {
+                ControllerError::new(
+                    GetInfoError,
+                    &format!("Card {} has no port {}", card_name, port),
+                )
+            })
+    }
+
+    /// Add `offset` microseconds to the latency of the devices using `port` on card
+    /// `card_name`. Positive values account for delays the server can't see, e.g. in a TV.
+    /// Fails like `get_port_latency_offset` when the card or port doesn't exist
+    pub fn set_port_latency_offset(
+        &mut self,
+        card_name: &str,
+        port: &str,
+        offset: i64,
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation("set_port_latency_offset", None, Some(card_name));
+        self.get_port_latency_offset(card_name, port)?;
+        super::succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            introspect.set_port_latency_offset(card_name, port, offset, done)
+        })
+    }
+}
+
+// the latency offset of the card port behind the device's active port
+fn port_offset(device: &DeviceInfo, cards: &[CardInfo]) -> Option<i64> {
+    let port = device.active_port.as_ref()?.name.as_ref()?;
+    let card = cards.iter().find(|card| Some(card.index) == device.card)?;
+    card.ports
+        .iter()
+        .find(|p| p.name.as_ref() == Some(port))
+        .map(|p| p.latency_offset)
+}
@@ -0,0 +1,424 @@
+/// An in-memory stand-in for `SinkController` and `SourceController`
+///
+/// `MockController` keeps a list of devices and applications that tests set up front and may
+/// change at any time through the public fields. Setters change that state the way the server
+/// would, and every call made through `DeviceControl` or `AppControl` is recorded so tests can
+/// assert on what their code asked for.
+/// ```
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::mock::{Call, MockController};
+/// use pulsectl::controllers::types::{ApplicationInfoBuilder, DeviceInfoBuilder};
+/// use pulsectl::controllers::{AppControl, DeviceControl};
+///
+/// fn main() {
+///     let mut mock = MockController::new()
+///         .with_device(DeviceInfoBuilder::new(0, "speakers").build())
+///         .with_device(DeviceInfoBuilder::new(1, "headphones").build())
+///         .with_app(ApplicationInfoBuilder::new(7, "Playback").build())
+///         .with_default("speakers");
+///
+///     mock.move_app_by_name(7, "headphones").unwrap();
+///
+///     assert_eq!(mock.get_app_by_index(7).unwrap().connection_id, 1);
+///     assert_eq!(mock.calls()[0], Call::MoveAppByName(7, "headphones".to_string()));
+/// }
+/// ```
+use pulse::volume::{ChannelVolumes, Volume};
+
+use super::errors::{ControllerError, ControllerErrorType::*};
+use super::types::{ApplicationInfo, DeviceInfo};
+use super::{volume_from_percent, AppControl, DeviceControl};
+
+/// A single call made on a `MockController`, with its arguments
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    GetDefaultDevice,
+    SetDefaultDevice(String),
+    ListDevices,
+    GetDeviceByIndex(u32),
+    GetDeviceByName(String),
+    SetDeviceVolumeByIndex(u32, ChannelVolumes),
+    SetDeviceVolumeByName(String, ChannelVolumes),
+    SetDeviceMuteByIndex(u32, bool),
+    SetDeviceMuteByName(String, bool),
+    SetDevicePortByIndex(u32, String),
+    SetDevicePortByName(String, String),
+    IncreaseDeviceVolumeByPercent(u32, f64),
+    DecreaseDeviceVolumeByPercent(u32, f64),
+    ListApplications,
+    GetAppByIndex(u32),
+    SetAppVolume(u32, ChannelVolumes),
+    IncreaseAppVolumeByPercent(u32, f64),
+    DecreaseAppVolumeByPercent(u32, f64),
+    MoveAppByIndex(u32, u32),
+    MoveAppByName(u32, String),
+    SetAppMute(u32, bool),
+}
+
+#[derive(Default)]
+pub struct MockController {
+    pub devices: Vec<DeviceInfo>,
+    pub applications: Vec<ApplicationInfo>,
+    /// Name of the default device.
+    pub default_device: Option<String>,
+    /// While set every call that can fail returns an error, to exercise error paths.
+    pub fail: bool,
+    calls: Vec<Call>,
+}
+
+impl MockController {
+    pub fn new() -> Self {
+        MockController::default()
+    }
+
+    pub fn with_device(mut self, device: DeviceInfo) -> Self {
+        self.devices.push(device);
+        self
+    }
+
+    pub fn with_app(mut self, app: ApplicationInfo) -> Self {
+        self.applications.push(app);
+        self
+    }
+
+    pub fn with_default(mut self, name: &str) -> Self {
+        self.default_device = Some(name.to_string());
+        self
+    }
+
+    /// Every call made so far, oldest first
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// Return the recorded calls and start recording from scratch
+    pub fn take_calls(&mut self) -> Vec<Call> {
+        std::mem::take(&mut self.calls)
+    }
+
+    // record the call and fail it if the mock was told to
+    fn record(&mut self, call: Call) -> Result<(), ControllerError> {
+        self.calls.push(call);
+        if self.fail {
+            Err(ControllerError::new(
+                GetInfoError,
+                "MockController was told to fail",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn device_mut(&mut self, index: u32) -> Option<&mut DeviceInfo> {
+        self.devices.iter_mut().find(|d| d.index == index)
+    }
+
+    fn device_by_name_mut(&mut self, name: &str) -> Option<&mut DeviceInfo> {
+        self.devices
+            .iter_mut()
+            .find(|d| d.name.as_deref() == Some(name))
+    }
+
+    fn app_mut(&mut self, index: u32) -> Option<&mut ApplicationInfo> {
+        self.applications.iter_mut().find(|a| a.index == index)
+    }
+}
+
+impl DeviceControl<DeviceInfo> for MockController {
+    fn get_default_device(&mut self) -> Result<DeviceInfo, ControllerError> {
+        self.record(Call::GetDefaultDevice)?;
+        let name = self.default_device.clone().unwrap_or_default();
+        self.devices
+            .iter()
+            .find(|d| d.name.as_ref() == Some(&name))
+            .cloned()
+            .ok_or_else(|| ControllerError::new(GetInfoError, "No default device is set"))
+    }
+    fn set_default_device(&mut self, name: &str) -> Result<bool, ControllerError> {
+        self.record(Call::SetDefaultDevice(name.to_string()))?;
+        if self.device_by_name_mut(name).is_none() {
+            return Ok(false);
+        }
+        self.default_device = Some(name.to_string());
+        Ok(true)
+    }
+
+    fn list_devices(&mut self) -> Result<Vec<DeviceInfo>, ControllerError> {
+        self.record(Call::ListDevices)?;
+        Ok(self.devices.clone())
+    }
+    fn get_device_by_index(&mut self, index: u32) -> Result<DeviceInfo, ControllerError> {
+        self.record(Call::GetDeviceByIndex(index))?;
+        self.device_mut(index)
+            .map(|d| d.clone())
+            .ok_or_else(|| ControllerError::new(GetInfoError, "No device with that index"))
+    }
+    fn get_device_by_name(&mut self, name: &str) -> Result<DeviceInfo, ControllerError> {
+        self.record(Call::GetDeviceByName(name.to_string()))?;
+        self.device_by_name_mut(name)
+            .map(|d| d.clone())
+            .ok_or_else(|| ControllerError::new(GetInfoError, "No device with that name"))
+    }
+    fn set_device_volume_by_index(&mut self, index: u32, volume: &ChannelVolumes) {
+        self.calls
+            .push(Call::SetDeviceVolumeByIndex(index, *volume));
+        if let Some(device) = self.device_mut(index) {
+            device.volume = *volume;
+        }
+    }
+    fn set_device_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        self.calls
+            .push(Call::SetDeviceVolumeByName(name.to_string(), *volume));
+        if let Some(device) = self.device_by_name_mut(name) {
+            device.volume = *volume;
+        }
+    }
+    fn set_device_mute_by_index(
+        &mut self,
+        index: u32,
+        mute: bool,
+    ) -> Result<bool, ControllerError> {
+        self.record(Call::SetDeviceMuteByIndex(index, mute))?;
+        Ok(self.device_mut(index).map(|d| d.mute = mute).is_some())
+    }
+    fn set_device_mute_by_name(&mut self, name: &str, mute: bool) -> Result<bool, ControllerError> {
+        self.record(Call::SetDeviceMuteByName(name.to_string(), mute))?;
+        Ok(self
+            .device_by_name_mut(name)
+            .map(|d| d.mute = mute)
+            .is_some())
+    }
+    fn set_device_port_by_index(
+        &mut self,
+        index: u32,
+        port: &str,
+    ) -> Result<bool, ControllerError> {
+        self.record(Call::SetDevicePortByIndex(index, port.to_string()))?;
+        Ok(self.device_mut(index).is_some_and(|d| select_port(d, port)))
+    }
+    fn set_device_port_by_name(&mut self, name: &str, port: &str) -> Result<bool, ControllerError> {
+        self.record(Call::SetDevicePortByName(
+            name.to_string(),
+            port.to_string(),
+        ))?;
+        Ok(self
+            .device_by_name_mut(name)
+            .is_some_and(|d| select_port(d, port)))
+    }
+    fn increase_device_volume_by_percent(&mut self, index: u32, delta: f64) {
+        self.calls
+            .push(Call::IncreaseDeviceVolumeByPercent(index, delta));
+        if let Some(device) = self.device_mut(index) {
+            device
+                .volume
+                .increase(Volume(volume_from_percent(delta) as u32));
+        }
+    }
+    fn decrease_device_volume_by_percent(&mut self, index: u32, delta: f64) {
+        self.calls
+            .push(Call::DecreaseDeviceVolumeByPercent(index, delta));
+        if let Some(device) = self.device_mut(index) {
+            device
+                .volume
+                .decrease(Volume(volume_from_percent(delta) as u32));
+        }
+    }
+}
+
+impl AppControl<ApplicationInfo> for MockController {
+    fn list_applications(&mut self) -> Result<Vec<ApplicationInfo>, ControllerError> {
+        self.record(Call::ListApplications)?;
+        Ok(self.applications.clone())
+    }
+
+    fn get_app_by_index(&mut self, index: u32) -> Result<ApplicationInfo, ControllerError> {
+        self.record(Call::GetAppByIndex(index))?;
+        self.app_mut(index)
+            .map(|a| a.clone())
+            .ok_or_else(|| ControllerError::new(GetInfoError, "No application with that index"))
+    }
+
+    fn set_app_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<bool, ControllerError> {
+        self.record(Call::SetAppVolume(index, *volume))?;
+        Ok(self.app_mut(index).map(|a| a.volume = *volume).is_some())
+    }
+
+    fn increase_app_volume_by_percent(&mut self, index: u32, delta: f64) {
+        self.calls
+            .push(Call::IncreaseAppVolumeByPercent(index, delta));
+        if let Some(app) = self.app_mut(index) {
+            app.volume
+                .increase(Volume(volume_from_percent(delta) as u32));
+        }
+    }
+
+    fn decrease_app_volume_by_percent(&mut self, index: u32, delta: f64) {
+        self.calls
+            .push(Call::DecreaseAppVolumeByPercent(index, delta));
+        if let Some(app) = self.app_mut(index) {
+            app.volume
+                .decrease(Volume(volume_from_percent(delta) as u32));
+        }
+    }
+
+    fn move_app_by_index(
+        &mut self,
+        stream_index: u32,
+        device_index: u32,
+    ) -> Result<bool, ControllerError> {
+        self.record(Call::MoveAppByIndex(stream_index, device_index))?;
+        if self.device_mut(device_index).is_none() {
+            return Ok(false);
+        }
+        Ok(self
+            .app_mut(stream_index)
+            .map(|a| a.connection_id = device_index)
+            .is_some())
+    }
+
+    fn move_app_by_name(
+        &mut self,
+        stream_index: u32,
+        device_name: &str,
+    ) -> Result<bool, ControllerError> {
+        self.record(Call::MoveAppByName(stream_index, device_name.to_string()))?;
+        let device_index = match self.device_by_name_mut(device_name) {
+            Some(device) => device.index,
+            None => return Ok(false),
+        };
+        Ok(self
+            .app_mut(stream_index)
+            .map(|a| a.connection_id = device_index)
+            .is_some())
+    }
+
+    fn set_app_mute(&mut self, index: u32, mute: bool) -> Result<bool, ControllerError> {
+        self.record(Call::SetAppMute(index, mute))?;
+        Ok(self.app_mut(index).map(|a| a.mute = mute).is_some())
+    }
+}
+
+// make `port` the active port of `device`, false if the device has no such port
+fn select_port(device: &mut DeviceInfo, port: &str) -> bool {
+    match device
+        .ports
+        .iter()
+        .find(|p| p.name.as_deref() == Some(port))
+    {
+        Some(found) => {
+            device.active_port = Some(found.clone());
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controllers::types::{ApplicationInfoBuilder, DeviceInfoBuilder, DevicePortInfo};
+    use pulse::def::PortAvailable;
+
+    fn port(name: &str) -> DevicePortInfo {
+        DevicePortInfo {
+            name: Some(name.to_string()),
+            description: None,
+            priority: 0,
+            available: PortAvailable::Yes,
+        }
+    }
+
+    fn mock() -> MockController {
+        MockController::new()
+            .with_device(
+                DeviceInfoBuilder::new(0, "speakers")
+                    .port(port("analog-output-speaker"))
+                    .port(port("analog-output-headphones"))
+                    .build(),
+            )
+            .with_device(DeviceInfoBuilder::new(1, "hdmi").build())
+            .with_app(ApplicationInfoBuilder::new(7, "Playback").build())
+            .with_default("speakers")
+    }
+
+    #[test]
+    fn setters_change_the_state_like_the_server() {
+        let mut mock = mock();
+        assert!(mock.set_default_device("hdmi").unwrap());
+        assert_eq!(mock.get_default_device().unwrap().index, 1);
+
+        assert!(mock.set_device_mute_by_name("speakers", true).unwrap());
+        assert!(mock.get_device_by_index(0).unwrap().mute);
+
+        assert!(mock
+            .set_device_port_by_index(0, "analog-output-headphones")
+            .unwrap());
+        let active = mock.get_device_by_name("speakers").unwrap().active_port;
+        assert_eq!(
+            active.and_then(|p| p.name).as_deref(),
+            Some("analog-output-headphones")
+        );
+
+        assert!(mock.move_app_by_index(7, 1).unwrap());
+        assert!(mock.set_app_mute(7, true).unwrap());
+        let app = mock.get_app_by_index(7).unwrap();
+        assert_eq!(app.connection_id, 1);
+        assert!(app.mute);
+    }
+
+    #[test]
+    fn unknown_targets_are_refused_without_changes() {
+        let mut mock = mock();
+        assert!(!mock.set_default_device("bluetooth").unwrap());
+        assert_eq!(mock.default_device.as_deref(), Some("speakers"));
+        assert!(!mock.set_device_port_by_name("hdmi", "nope").unwrap());
+        assert!(!mock.set_device_mute_by_index(5, true).unwrap());
+        assert!(!mock.move_app_by_name(7, "bluetooth").unwrap());
+        assert_eq!(mock.applications[0].connection_id, 0);
+        assert!(!mock.set_app_mute(8, true).unwrap());
+        assert!(mock.get_device_by_index(5).is_err());
+        assert!(mock.get_app_by_index(8).is_err());
+    }
+
+    #[test]
+    fn percent_changes_move_the_volume() {
+        let mut mock = mock();
+        let before = mock.devices[0].volume.avg();
+        mock.decrease_device_volume_by_percent(0, 0.1);
+        let after = mock.devices[0].volume.avg();
+        assert_eq!(before.0 - after.0, volume_from_percent(0.1) as u32);
+
+        mock.increase_app_volume_by_percent(7, 0.1);
+        assert!(mock.applications[0].volume.avg() > before);
+    }
+
+    #[test]
+    fn calls_are_recorded_in_order() {
+        let mut mock = mock();
+        mock.list_devices().unwrap();
+        mock.set_device_volume_by_index(1, &mock.devices[1].volume.clone());
+        mock.move_app_by_name(7, "hdmi").unwrap();
+        assert_eq!(mock.calls().len(), 3);
+        assert_eq!(mock.calls()[0], Call::ListDevices);
+        assert_eq!(
+            mock.take_calls()[2],
+            Call::MoveAppByName(7, "hdmi".to_string())
+        );
+        assert!(mock.calls().is_empty());
+    }
+
+    #[test]
+    fn failing_mocks_record_and_return_errors() {
+        let mut mock = mock();
+        mock.fail = true;
+        assert!(mock.list_devices().is_err());
+        assert!(mock.set_app_mute(7, true).is_err());
+        assert!(!mock.applications[0].mute);
+        assert_eq!(mock.calls().len(), 2);
+    }
+}
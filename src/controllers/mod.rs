@@ -7,24 +7,50 @@
 /// if you want to manipulate recording devices such as microphone volume,
 /// you'll need to use a `SourceController`. Both of these implement the same api, defined by
 /// the traits DeviceControl and AppControl
-
+///
+/// Both are the same `DeviceController`, parameterised by the `Direction` it works on.
+use std::borrow;
 use std::cell::RefCell;
 use std::clone::Clone;
+use std::marker::PhantomData;
 use std::rc::Rc;
+use std::time::Duration;
 
 use pulse::{
     callbacks::ListResult,
-    context::introspect,
+    context::introspect::{self, Introspector},
+    def::INVALID_INDEX,
+    operation::Operation,
     volume::{ChannelVolumes, Volume},
 };
 
+use direction::Done;
 use errors::{ControllerError, ControllerErrorType::*};
-use types::{ApplicationInfo, DeviceInfo, ServerInfo};
+use identity::DeviceId;
+use search::DeviceMatch;
+use types::{ApplicationInfo, CardInfo, DeviceInfo, ModuleInfo, ServerInfo};
 
-use crate::Handler;
+use crate::fade::{self, Fade, FadeCurve, FadeTarget};
+use crate::trace;
+use crate::{Handler, HandlerRef};
 
+pub use batch::Batch;
+pub use direction::{Capture, Direction, Playback};
+
+pub mod batch;
+pub mod bluetooth;
+pub mod direction;
+pub mod echo_cancel;
 pub(crate) mod errors;
+pub mod formats;
+pub mod identity;
+pub mod ladspa;
+pub mod latency;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod search;
 pub mod types;
+pub mod virtual_devices;
 
 pub trait DeviceControl<T> {
     fn get_default_device(&mut self) -> Result<T, ControllerError>;
@@ -37,12 +63,85 @@ pub trait DeviceControl<T> {
     fn set_device_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes);
     fn increase_device_volume_by_percent(&mut self, index: u32, delta: f64);
     fn decrease_device_volume_by_percent(&mut self, index: u32, delta: f64);
+
+    // the setters below came after the first release, implementors that predate them get
+    // an error instead of a compile failure
+
+    fn set_device_mute_by_index(
+        &mut self,
+        _index: u32,
+        _mute: bool,
+    ) -> Result<bool, ControllerError> {
+        Err(unsupported("set_device_mute_by_index"))
+    }
+    fn set_device_mute_by_name(
+        &mut self,
+        _name: &str,
+        _mute: bool,
+    ) -> Result<bool, ControllerError> {
+        Err(unsupported("set_device_mute_by_name"))
+    }
+    fn set_device_port_by_index(
+        &mut self,
+        _index: u32,
+        _port: &str,
+    ) -> Result<bool, ControllerError> {
+        Err(unsupported("set_device_port_by_index"))
+    }
+    fn set_device_port_by_name(
+        &mut self,
+        _name: &str,
+        _port: &str,
+    ) -> Result<bool, ControllerError> {
+        Err(unsupported("set_device_port_by_name"))
+    }
+
+    /// Devices whose description, name, product name or active port matches `query`,
+    /// best match first, see `search` for how matches are ranked
+    fn find_devices(&mut self, query: &str) -> Result<Vec<DeviceMatch<T>>, ControllerError>
+    where
+        T: borrow::Borrow<DeviceInfo>,
+    {
+        Ok(search::rank_devices(self.list_devices()?, query))
+    }
+
+    /// The device `id` was taken from, preferring one that still has the same name when
+    /// several fit
+    fn get_device_by_id(&mut self, id: &DeviceId) -> Result<T, ControllerError>
+    where
+        T: borrow::Borrow<DeviceInfo>,
+    {
+        let mut candidates: Vec<T> = self
+            .list_devices()?
+            .into_iter()
+            .filter(|device| id.matches(device.borrow()))
+            .collect();
+        let named = candidates
+            .iter()
+            .position(|device| device.borrow().name.is_some() && device.borrow().name == id.name);
+        match named {
+            Some(position) => Ok(candidates.swap_remove(position)),
+            None if !candidates.is_empty() => Ok(candidates.swap_remove(0)),
+            None => Err(ControllerError::new(
+                GetInfoError,
+                "No device matches the requested id",
+            )),
+        }
+    }
 }
 
 pub trait AppControl<T> {
     fn list_applications(&mut self) -> Result<Vec<T>, ControllerError>;
 
     fn get_app_by_index(&mut self, index: u32) -> Result<T, ControllerError>;
+    /// Added after the first release, the default only reports that it's unsupported
+    fn set_app_volume(
+        &mut self,
+        _index: u32,
+        _volume: &ChannelVolumes,
+    ) -> Result<bool, ControllerError> {
+        Err(unsupported("set_app_volume"))
+    }
     fn increase_app_volume_by_percent(&mut self, index: u32, delta: f64);
     fn decrease_app_volume_by_percent(&mut self, index: u32, delta: f64);
 
@@ -59,233 +158,333 @@ pub trait AppControl<T> {
     fn set_app_mute(&mut self, index: u32, mute: bool) -> Result<bool, ControllerError>;
 }
 
+fn unsupported(method: &str) -> ControllerError {
+    ControllerError::new(
+        UnsupportedError,
+        &format!("{} is not implemented by this controller", method),
+    )
+}
+
 fn volume_from_percent(volume: f64) -> f64 {
-    ((volume * 100.0) * (f64::from(pulse::volume::VOLUME_NORM.0) / 100.0))
+    (volume * 100.0) * (f64::from(pulse::volume::VOLUME_NORM.0) / 100.0)
 }
 
-pub struct SinkController {
-    pub handler: Handler,
+/// Controls the devices and applications of one `Direction`, `SinkController` and
+/// `SourceController` are the two flavours of it
+///
+/// The handler is usually owned, but anything that lends out a `Handler` works, which lets a
+/// controller for the other direction run over the same connection through `playback()` and
+/// `capture()`.
+pub struct DeviceController<D, H = Handler> {
+    pub handler: H,
+    direction: PhantomData<D>,
 }
 
-impl SinkController {
+/// Playback devices (sinks) and the applications playing to them (sink inputs)
+pub type SinkController = DeviceController<Playback>;
+/// Recording devices (sources) and the applications recording from them (source outputs)
+pub type SourceController = DeviceController<Capture>;
+
+impl<D: Direction> DeviceController<D> {
     pub fn create() -> Self {
-        let handler = Handler::connect("SinkController").expect("Unable to connect to PulseAudio");
-        SinkController { handler }
+        let handler = Handler::connect(D::CLIENT_NAME).expect("Unable to connect to PulseAudio");
+        DeviceController::from_handler(handler)
+    }
+}
+
+impl<D: Direction, H: HandlerRef> DeviceController<D, H> {
+    /// Use an already connected handler, e.g. one set up through `HandlerBuilder`
+    pub fn from_handler(handler: H) -> Self {
+        DeviceController {
+            handler,
+            direction: PhantomData,
+        }
+    }
+
+    /// Work on playback devices and applications over this controller's connection
+    pub fn playback(&mut self) -> DeviceController<Playback, &mut H> {
+        DeviceController::from_handler(&mut self.handler)
+    }
+
+    /// Work on recording devices and applications over this controller's connection
+    pub fn capture(&mut self) -> DeviceController<Capture, &mut H> {
+        DeviceController::from_handler(&mut self.handler)
+    }
+
+    /// Queue several changes and wait for all of them at once, see `Batch`
+    pub fn batch(&mut self) -> Batch<'_, D, H> {
+        Batch::new(self)
     }
 
     pub fn get_server_info(&mut self) -> Result<ServerInfo, ControllerError> {
+        let _span = trace::operation("get_server_info", None, None);
         let server = Rc::new(RefCell::new(Some(None)));
         let server_ref = server.clone();
 
-        let op = self.handler.introspect.get_server_info(move |res| {
+        let mut handler = self.handler.borrow_handler();
+        let op = handler.introspect.get_server_info(move |res| {
             server_ref
                 .borrow_mut()
                 .as_mut()
                 .unwrap()
                 .replace(res.into());
         });
-        self.handler.wait_for_operation(op)?;
+        handler.wait_for_operation(op)?;
         let mut result = server.borrow_mut();
         result.take().unwrap().ok_or(ControllerError::new(
             GetInfoError,
             "Error getting information about the server",
         ))
     }
+
+    /// Gradually move a device's volume to `target`, replacing any fade already running on it
+    pub fn fade_device_volume(
+        &mut self,
+        index: u32,
+        target: &ChannelVolumes,
+        duration: Duration,
+        curve: FadeCurve,
+    ) -> Result<Fade, ControllerError> {
+        let device = self.get_device_by_index(index)?;
+        Ok(fade::start(
+            &mut self.handler.borrow_handler(),
+            FadeTarget::new(D::DEVICE, index),
+            device.volume,
+            *target,
+            duration,
+            curve,
+        ))
+    }
+
+    /// Gradually move an application's volume to `target`, replacing any fade already running on it
+    pub fn fade_app_volume(
+        &mut self,
+        index: u32,
+        target: &ChannelVolumes,
+        duration: Duration,
+        curve: FadeCurve,
+    ) -> Result<Fade, ControllerError> {
+        let app = self.get_app_by_index(index)?;
+        Ok(fade::start(
+            &mut self.handler.borrow_handler(),
+            FadeTarget::new(D::APP, index),
+            app.volume,
+            *target,
+            duration,
+            curve,
+        ))
+    }
+
+    // change a device's volume relative to where it is now
+    fn step_device_volume(&mut self, index: u32, delta: f64, up: bool) {
+        let _span = trace::operation(D::CALLS.set_device_volume_by_index, Some(index), None);
+        let mut dev_ref = self
+            .get_device_by_index(index)
+            .expect("Could not find device specified");
+        let new_vol = Volume(volume_from_percent(delta) as u32);
+        debug!(step = %new_vol.print_verbose(true), "changing volume");
+        let volumes = if up {
+            dev_ref.volume.increase(new_vol)
+        } else {
+            dev_ref.volume.decrease(new_vol)
+        }
+        .expect("Volume couldn't be set");
+        let mut handler = self.handler.borrow_handler();
+        let op = D::set_device_volume_by_index(&mut handler.introspect, index, volumes, None);
+        handler.wait_for_operation(op).expect("error");
+    }
+
+    // change an application's volume relative to where it is now
+    fn step_app_volume(&mut self, index: u32, delta: f64, up: bool) {
+        let _span = trace::operation(D::CALLS.set_app_volume, Some(index), None);
+        let mut app_ref = self
+            .get_app_by_index(index)
+            .expect("Could not find device specified");
+        let new_vol = Volume(volume_from_percent(delta) as u32);
+        debug!(step = %new_vol.print_verbose(true), "changing volume");
+        let volumes = if up {
+            app_ref.volume.increase(new_vol)
+        } else {
+            app_ref.volume.decrease(new_vol)
+        }
+        .expect("Volume couldn't be set");
+        let mut handler = self.handler.borrow_handler();
+        let op = D::set_app_volume(&mut handler.introspect, index, volumes, None);
+        handler.wait_for_operation(op).expect("error");
+    }
+}
+
+// start a query and gather every item it reports
+fn collect<T: 'static, G: ?Sized>(
+    handler: &mut Handler,
+    start: impl FnOnce(&Introspector, Box<dyn FnMut(T)>) -> Operation<G>,
+) -> Result<Vec<T>, ControllerError> {
+    let list = Rc::new(RefCell::new(Vec::new()));
+    let list_ref = list.clone();
+    let op = start(
+        &handler.introspect,
+        Box::new(move |item| list_ref.borrow_mut().push(item)),
+    );
+    handler.wait_for_operation(op)?;
+    let result = list.replace(Vec::new());
+    Ok(result)
+}
+
+// start an operation that only reports whether it worked and wait for the answer
+fn succeeded(
+    handler: &mut Handler,
+    start: impl FnOnce(&mut Introspector, Done) -> Operation<dyn FnMut(bool)>,
+) -> Result<bool, ControllerError> {
+    let success = Rc::new(RefCell::new(false));
+    let success_ref = success.clone();
+    let op = start(
+        &mut handler.introspect,
+        Some(Box::new(move |res| {
+            success_ref.borrow_mut().clone_from(&res)
+        })),
+    );
+    handler.wait_for_operation(op)?;
+    let result = *success.borrow_mut();
+    Ok(result)
 }
 
-impl DeviceControl<DeviceInfo> for SinkController {
+impl<D: Direction, H: HandlerRef> DeviceControl<DeviceInfo> for DeviceController<D, H> {
     fn get_default_device(&mut self) -> Result<DeviceInfo, ControllerError> {
         let server_info = self.get_server_info();
         match server_info {
-            Ok(info) => self.get_device_by_name(info.default_sink_name.unwrap().as_ref()),
+            Ok(info) => self.get_device_by_name(D::default_device_name(&info).unwrap().as_ref()),
             Err(e) => Err(e),
         }
     }
     fn set_default_device(&mut self, name: &str) -> Result<bool, ControllerError> {
+        let _span = trace::operation(D::CALLS.set_default_device, None, Some(name));
         let success = Rc::new(RefCell::new(false));
         let success_ref = success.clone();
 
-        let op = self
-            .handler
-            .context
-            .borrow_mut()
-            .set_default_sink(name, move |res| success_ref.borrow_mut().clone_from(&res));
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
+        let mut handler = self.handler.borrow_handler();
+        let op = D::set_default_device(
+            &mut handler.context.borrow_mut(),
+            name,
+            Box::new(move |res| success_ref.borrow_mut().clone_from(&res)),
+        );
+        handler.wait_for_operation(op)?;
+        let result = *success.borrow_mut();
         Ok(result)
     }
 
     fn list_devices(&mut self) -> Result<Vec<DeviceInfo>, ControllerError> {
-        let list = Rc::new(RefCell::new(Some(Vec::new())));
-        let list_ref = list.clone();
-
-        let op = self.handler.introspect.get_sink_info_list(
-            move |sink_list: ListResult<&introspect::SinkInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    list_ref.borrow_mut().as_mut().unwrap().push(item.into());
-                }
-            },
-        );
-        self.handler.wait_for_operation(op)?;
-        let mut result = list.borrow_mut();
-        result.take().ok_or(ControllerError::new(
-            GetInfoError,
-            "Error getting device list",
-        ))
+        let _span = trace::operation(D::CALLS.list_devices, None, None);
+        collect(&mut self.handler.borrow_handler(), D::list_devices)
     }
     fn get_device_by_index(&mut self, index: u32) -> Result<DeviceInfo, ControllerError> {
-        let device = Rc::new(RefCell::new(Some(None)));
-        let dev_ref = device.clone();
-        let op = self.handler.introspect.get_sink_info_by_index(
-            index,
-            move |sink_list: ListResult<&introspect::SinkInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    dev_ref.borrow_mut().as_mut().unwrap().replace(item.into());
-                }
-            },
-        );
-        self.handler.wait_for_operation(op)?;
-        let mut result = device.borrow_mut();
-        result.take().unwrap().ok_or(ControllerError::new(
+        let _span = trace::operation(D::CALLS.get_device_by_index, Some(index), None);
+        collect(&mut self.handler.borrow_handler(), |introspect, each| {
+            D::get_device_by_index(introspect, index, each)
+        })?
+        .pop()
+        .ok_or(ControllerError::new(
             GetInfoError,
             "Error getting requested device",
         ))
     }
     fn get_device_by_name(&mut self, name: &str) -> Result<DeviceInfo, ControllerError> {
-        let device = Rc::new(RefCell::new(Some(None)));
-        let dev_ref = device.clone();
-        let op = self.handler.introspect.get_sink_info_by_name(
-            name,
-            move |sink_list: ListResult<&introspect::SinkInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    dev_ref.borrow_mut().as_mut().unwrap().replace(item.into());
-                }
-            },
-        );
-        self.handler.wait_for_operation(op)?;
-        let mut result = device.borrow_mut();
-        result.take().unwrap().ok_or(ControllerError::new(
+        let _span = trace::operation(D::CALLS.get_device_by_name, None, Some(name));
+        collect(&mut self.handler.borrow_handler(), |introspect, each| {
+            D::get_device_by_name(introspect, name, each)
+        })?
+        .pop()
+        .ok_or(ControllerError::new(
             GetInfoError,
             "Error getting requested device",
         ))
     }
 
     fn set_device_volume_by_index(&mut self, index: u32, volume: &ChannelVolumes) {
-        let op = self
-            .handler
-            .introspect
-            .set_sink_volume_by_index(index, volume, None);
-        self.handler.wait_for_operation(op).expect("error");
+        let _span = trace::operation(D::CALLS.set_device_volume_by_index, Some(index), None);
+        let mut handler = self.handler.borrow_handler();
+        let op = D::set_device_volume_by_index(&mut handler.introspect, index, volume, None);
+        handler.wait_for_operation(op).expect("error");
     }
     fn set_device_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
-        let op = self
-            .handler
-            .introspect
-            .set_sink_volume_by_name(name, volume, None);
-        self.handler.wait_for_operation(op).expect("error");
+        let _span = trace::operation(D::CALLS.set_device_volume_by_name, None, Some(name));
+        let mut handler = self.handler.borrow_handler();
+        let op = D::set_device_volume_by_name(&mut handler.introspect, name, volume, None);
+        handler.wait_for_operation(op).expect("error");
+    }
+    fn set_device_mute_by_index(
+        &mut self,
+        index: u32,
+        mute: bool,
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation(D::CALLS.set_device_mute_by_index, Some(index), None);
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::set_device_mute_by_index(introspect, index, mute, done)
+        })
+    }
+    fn set_device_mute_by_name(&mut self, name: &str, mute: bool) -> Result<bool, ControllerError> {
+        let _span = trace::operation(D::CALLS.set_device_mute_by_name, None, Some(name));
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::set_device_mute_by_name(introspect, name, mute, done)
+        })
+    }
+    fn set_device_port_by_index(
+        &mut self,
+        index: u32,
+        port: &str,
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation(D::CALLS.set_device_port_by_index, Some(index), None);
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::set_device_port_by_index(introspect, index, port, done)
+        })
+    }
+    fn set_device_port_by_name(&mut self, name: &str, port: &str) -> Result<bool, ControllerError> {
+        let _span = trace::operation(D::CALLS.set_device_port_by_name, None, Some(name));
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::set_device_port_by_name(introspect, name, port, done)
+        })
     }
     fn increase_device_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut dev_ref = self
-            .get_device_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = dev_ref
-            .volume
-            .increase(new_vol)
-            .expect("Volume couldn't be set");
-        let op = self
-            .handler
-            .introspect
-            .set_sink_volume_by_index(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
+        self.step_device_volume(index, delta, true);
     }
     fn decrease_device_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut dev_ref = self
-            .get_device_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = dev_ref.volume.decrease(new_vol).unwrap();
-        let op = self
-            .handler
-            .introspect
-            .set_sink_volume_by_index(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
+        self.step_device_volume(index, delta, false);
     }
 }
 
-impl AppControl<ApplicationInfo> for SinkController {
+impl<D: Direction, H: HandlerRef> AppControl<ApplicationInfo> for DeviceController<D, H> {
     fn list_applications(&mut self) -> Result<Vec<ApplicationInfo>, ControllerError> {
-        let list = Rc::new(RefCell::new(Some(Vec::new())));
-        let list_ref = list.clone();
-
-        let op = self.handler.introspect.get_sink_input_info_list(
-            move |sink_list: ListResult<&introspect::SinkInputInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    list_ref.borrow_mut().as_mut().unwrap().push(item.into());
-                }
-            },
-        );
-        self.handler.wait_for_operation(op)?;
-        let mut result = list.borrow_mut();
-        result.take().ok_or(ControllerError::new(
-            GetInfoError,
-            "Error getting application list",
-        ))
+        let _span = trace::operation(D::CALLS.list_applications, None, None);
+        collect(&mut self.handler.borrow_handler(), D::list_applications)
     }
 
     fn get_app_by_index(&mut self, index: u32) -> Result<ApplicationInfo, ControllerError> {
-        let app = Rc::new(RefCell::new(Some(None)));
-        let app_ref = app.clone();
-        let op = self.handler.introspect.get_sink_input_info(
-            index,
-            move |sink_list: ListResult<&introspect::SinkInputInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    app_ref.borrow_mut().as_mut().unwrap().replace(item.into());
-                }
-            },
-        );
-        self.handler.wait_for_operation(op)?;
-        let mut result = app.borrow_mut();
-        result.take().unwrap().ok_or(ControllerError::new(
+        let _span = trace::operation(D::CALLS.get_app_by_index, Some(index), None);
+        collect(&mut self.handler.borrow_handler(), |introspect, each| {
+            D::get_app_by_index(introspect, index, each)
+        })?
+        .pop()
+        .ok_or(ControllerError::new(
             GetInfoError,
             "Error getting requested app",
         ))
     }
 
+    fn set_app_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation(D::CALLS.set_app_volume, Some(index), None);
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::set_app_volume(introspect, index, volume, done)
+        })
+    }
     fn increase_app_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut app_ref = self
-            .get_app_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = app_ref
-            .volume
-            .increase(new_vol)
-            .expect("Volume couldn't be set");
-        let op = self
-            .handler
-            .introspect
-            .set_sink_input_volume(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
+        self.step_app_volume(index, delta, true);
     }
 
     fn decrease_app_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut app_ref = self
-            .get_app_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = app_ref
-            .volume
-            .decrease(new_vol)
-            .expect("Volume couldn't be set");
-        let op = self
-            .handler
-            .introspect
-            .set_sink_input_volume(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
+        self.step_app_volume(index, delta, false);
     }
 
     fn move_app_by_index(
@@ -293,18 +492,10 @@ impl AppControl<ApplicationInfo> for SinkController {
         stream_index: u32,
         device_index: u32,
     ) -> Result<bool, ControllerError> {
-        let success = Rc::new(RefCell::new(false));
-        let success_ref = success.clone();
-        let op = self.handler.introspect.move_sink_input_by_index(
-            stream_index,
-            device_index,
-            Some(Box::new(move |res| {
-                success_ref.borrow_mut().clone_from(&res)
-            })),
-        );
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
-        Ok(result)
+        let _span = trace::operation(D::CALLS.move_app_by_index, Some(stream_index), None);
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::move_app_by_index(introspect, stream_index, device_index, done)
+        })
     }
 
     fn move_app_by_name(
@@ -312,311 +503,226 @@ impl AppControl<ApplicationInfo> for SinkController {
         stream_index: u32,
         device_name: &str,
     ) -> Result<bool, ControllerError> {
-        let success = Rc::new(RefCell::new(false));
-        let success_ref = success.clone();
-        let op = self.handler.introspect.move_sink_input_by_name(
-            stream_index,
-            device_name,
-            Some(Box::new(move |res| {
-                success_ref.borrow_mut().clone_from(&res)
-            })),
+        let _span = trace::operation(
+            D::CALLS.move_app_by_name,
+            Some(stream_index),
+            Some(device_name),
         );
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
-        Ok(result)
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::move_app_by_name(introspect, stream_index, device_name, done)
+        })
     }
 
     fn set_app_mute(&mut self, index: u32, mute: bool) -> Result<bool, ControllerError> {
-        let success = Rc::new(RefCell::new(false));
-        let success_ref = success.clone();
-        let op = self.handler.introspect.set_sink_input_mute(
-            index,
-            mute,
-            Some(Box::new(move |res| {
-                success_ref.borrow_mut().clone_from(&res)
-            })),
-        );
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
-        Ok(result)
+        let _span = trace::operation(D::CALLS.set_app_mute, Some(index), None);
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            D::set_app_mute(introspect, index, mute, done)
+        })
     }
 }
 
-pub struct SourceController {
-    pub handler: Handler,
+/// Cards are the physical devices that sinks and sources belong to,
+/// a `CardController` lists them along with their profiles and ports
+pub struct CardController<H = Handler> {
+    pub handler: H,
 }
 
-impl SourceController {
+impl CardController {
     pub fn create() -> Self {
-        let handler = Handler::connect("SourceController").unwrap();
-        SourceController { handler }
-    }
-
-    pub fn get_server_info(&mut self) -> Result<ServerInfo, ControllerError> {
-        let server = Rc::new(RefCell::new(Some(None)));
-        let server_ref = server.clone();
-
-        let op = self.handler.introspect.get_server_info(move |res| {
-            server_ref
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .replace(res.into());
-        });
-        self.handler.wait_for_operation(op)?;
-        let mut result = server.borrow_mut();
-        result.take().unwrap().ok_or(ControllerError::new(
-            GetInfoError,
-            "Error getting application list",
-        ))
+        let handler = Handler::connect("CardController").expect("Unable to connect to PulseAudio");
+        CardController { handler }
     }
 }
 
-impl DeviceControl<DeviceInfo> for SourceController {
-    fn get_default_device(&mut self) -> Result<DeviceInfo, ControllerError> {
-        let server_info = self.get_server_info();
-        match server_info {
-            Ok(info) => self.get_device_by_name(info.default_sink_name.unwrap().as_ref()),
-            Err(e) => Err(e),
-        }
+impl<H: HandlerRef> CardController<H> {
+    /// Use an already connected handler, e.g. one set up through `HandlerBuilder`
+    pub fn from_handler(handler: H) -> Self {
+        CardController { handler }
     }
-    fn set_default_device(&mut self, name: &str) -> Result<bool, ControllerError> {
-        let success = Rc::new(RefCell::new(false));
-        let success_ref = success.clone();
 
-        let op = self
-            .handler
-            .context
-            .borrow_mut()
-            .set_default_source(name, move |res| success_ref.borrow_mut().clone_from(&res));
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
-        Ok(result)
-    }
-
-    fn list_devices(&mut self) -> Result<Vec<DeviceInfo>, ControllerError> {
+    pub fn list_cards(&mut self) -> Result<Vec<CardInfo>, ControllerError> {
+        let _span = trace::operation("get_card_info_list", None, None);
         let list = Rc::new(RefCell::new(Some(Vec::new())));
         let list_ref = list.clone();
 
-        let op = self.handler.introspect.get_source_info_list(
-            move |sink_list: ListResult<&introspect::SourceInfo>| {
-                if let ListResult::Item(item) = sink_list {
+        let mut handler = self.handler.borrow_handler();
+        let op = handler.introspect.get_card_info_list(
+            move |card_list: ListResult<&introspect::CardInfo>| {
+                if let ListResult::Item(item) = card_list {
                     list_ref.borrow_mut().as_mut().unwrap().push(item.into());
                 }
             },
         );
-        self.handler.wait_for_operation(op)?;
+        handler.wait_for_operation(op)?;
         let mut result = list.borrow_mut();
         result.take().ok_or(ControllerError::new(
             GetInfoError,
-            "Error getting application list",
+            "Error getting card list",
         ))
     }
-    fn get_device_by_index(&mut self, index: u32) -> Result<DeviceInfo, ControllerError> {
-        let device = Rc::new(RefCell::new(Some(None)));
-        let dev_ref = device.clone();
-        let op = self.handler.introspect.get_source_info_by_index(
+
+    pub fn get_card_by_index(&mut self, index: u32) -> Result<CardInfo, ControllerError> {
+        let _span = trace::operation("get_card_info_by_index", Some(index), None);
+        let card = Rc::new(RefCell::new(Some(None)));
+        let card_ref = card.clone();
+        let mut handler = self.handler.borrow_handler();
+        let op = handler.introspect.get_card_info_by_index(
             index,
-            move |sink_list: ListResult<&introspect::SourceInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    dev_ref.borrow_mut().as_mut().unwrap().replace(item.into());
+            move |card_list: ListResult<&introspect::CardInfo>| {
+                if let ListResult::Item(item) = card_list {
+                    card_ref.borrow_mut().as_mut().unwrap().replace(item.into());
                 }
             },
         );
-        self.handler.wait_for_operation(op)?;
-        let mut result = device.borrow_mut();
+        handler.wait_for_operation(op)?;
+        let mut result = card.borrow_mut();
         result.take().unwrap().ok_or(ControllerError::new(
             GetInfoError,
-            "Error getting application list",
+            "Error getting requested card",
         ))
     }
-    fn get_device_by_name(&mut self, name: &str) -> Result<DeviceInfo, ControllerError> {
-        let device = Rc::new(RefCell::new(Some(None)));
-        let dev_ref = device.clone();
-        let op = self.handler.introspect.get_source_info_by_name(
+
+    pub fn get_card_by_name(&mut self, name: &str) -> Result<CardInfo, ControllerError> {
+        let _span = trace::operation("get_card_info_by_name", None, Some(name));
+        let card = Rc::new(RefCell::new(Some(None)));
+        let card_ref = card.clone();
+        let mut handler = self.handler.borrow_handler();
+        let op = handler.introspect.get_card_info_by_name(
             name,
-            move |sink_list: ListResult<&introspect::SourceInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    dev_ref.borrow_mut().as_mut().unwrap().replace(item.into());
+            move |card_list: ListResult<&introspect::CardInfo>| {
+                if let ListResult::Item(item) = card_list {
+                    card_ref.borrow_mut().as_mut().unwrap().replace(item.into());
                 }
             },
         );
-        self.handler.wait_for_operation(op)?;
-        let mut result = device.borrow_mut();
+        handler.wait_for_operation(op)?;
+        let mut result = card.borrow_mut();
         result.take().unwrap().ok_or(ControllerError::new(
             GetInfoError,
-            "Error getting application list",
+            "Error getting requested card",
         ))
     }
 
-    fn set_device_volume_by_index(&mut self, index: u32, volume: &ChannelVolumes) {
-        let op = self
-            .handler
-            .introspect
-            .set_source_volume_by_index(index, volume, None);
-        self.handler.wait_for_operation(op).expect("error");
-    }
-    fn set_device_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
-        let op = self
-            .handler
-            .introspect
-            .set_source_volume_by_name(name, volume, None);
-        self.handler.wait_for_operation(op).expect("error");
+    pub fn set_card_profile_by_index(
+        &mut self,
+        index: u32,
+        profile: &str,
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation("set_card_profile_by_index", Some(index), Some(profile));
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            introspect.set_card_profile_by_index(index, profile, done)
+        })
     }
-    fn increase_device_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut dev_ref = self
-            .get_device_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = dev_ref
-            .volume
-            .increase(new_vol)
-            .expect("Volume couldn't be set");
-        let op = self
-            .handler
-            .introspect
-            .set_source_volume_by_index(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
+
+    pub fn set_card_profile_by_name(
+        &mut self,
+        name: &str,
+        profile: &str,
+    ) -> Result<bool, ControllerError> {
+        let _span = trace::operation("set_card_profile_by_name", None, Some(name));
+        succeeded(&mut self.handler.borrow_handler(), |introspect, done| {
+            introspect.set_card_profile_by_name(name, profile, done)
+        })
     }
-    fn decrease_device_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut dev_ref = self
-            .get_device_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = dev_ref.volume.decrease(new_vol).unwrap();
-        let op = self
-            .handler
-            .introspect
-            .set_source_volume_by_index(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
+}
+
+/// Modules make up most of what the server does, from the ALSA and Bluetooth devices to null
+/// sinks and network streaming. A `ModuleController` lists them and loads or unloads them at
+/// runtime, like `pactl load-module` does
+pub struct ModuleController<H = Handler> {
+    pub handler: H,
+}
+
+impl ModuleController {
+    pub fn create() -> Self {
+        let handler =
+            Handler::connect("ModuleController").expect("Unable to connect to PulseAudio");
+        ModuleController { handler }
     }
 }
 
-impl AppControl<ApplicationInfo> for SourceController {
-    fn list_applications(&mut self) -> Result<Vec<ApplicationInfo>, ControllerError> {
+impl<H: HandlerRef> ModuleController<H> {
+    /// Use an already connected handler, e.g. one set up through `HandlerBuilder`
+    pub fn from_handler(handler: H) -> Self {
+        ModuleController { handler }
+    }
+
+    pub fn list_modules(&mut self) -> Result<Vec<ModuleInfo>, ControllerError> {
+        let _span = trace::operation("get_module_info_list", None, None);
         let list = Rc::new(RefCell::new(Some(Vec::new())));
         let list_ref = list.clone();
 
-        let op = self.handler.introspect.get_source_output_info_list(
-            move |sink_list: ListResult<&introspect::SourceOutputInfo>| {
-                if let ListResult::Item(item) = sink_list {
+        let mut handler = self.handler.borrow_handler();
+        let op = handler.introspect.get_module_info_list(
+            move |module_list: ListResult<&introspect::ModuleInfo>| {
+                if let ListResult::Item(item) = module_list {
                     list_ref.borrow_mut().as_mut().unwrap().push(item.into());
                 }
             },
         );
-        self.handler.wait_for_operation(op)?;
+        handler.wait_for_operation(op)?;
         let mut result = list.borrow_mut();
         result.take().ok_or(ControllerError::new(
             GetInfoError,
-            "Error getting application list",
+            "Error getting module list",
         ))
     }
 
-    fn get_app_by_index(&mut self, index: u32) -> Result<ApplicationInfo, ControllerError> {
-        let app = Rc::new(RefCell::new(Some(None)));
-        let app_ref = app.clone();
-        let op = self.handler.introspect.get_source_output_info(
+    pub fn get_module_by_index(&mut self, index: u32) -> Result<ModuleInfo, ControllerError> {
+        let _span = trace::operation("get_module_info", Some(index), None);
+        let module = Rc::new(RefCell::new(Some(None)));
+        let module_ref = module.clone();
+        let mut handler = self.handler.borrow_handler();
+        let op = handler.introspect.get_module_info(
             index,
-            move |sink_list: ListResult<&introspect::SourceOutputInfo>| {
-                if let ListResult::Item(item) = sink_list {
-                    app_ref.borrow_mut().as_mut().unwrap().replace(item.into());
+            move |module_list: ListResult<&introspect::ModuleInfo>| {
+                if let ListResult::Item(item) = module_list {
+                    module_ref
+                        .borrow_mut()
+                        .as_mut()
+                        .unwrap()
+                        .replace(item.into());
                 }
             },
         );
-        self.handler.wait_for_operation(op)?;
-        let mut result = app.borrow_mut();
+        handler.wait_for_operation(op)?;
+        let mut result = module.borrow_mut();
         result.take().unwrap().ok_or(ControllerError::new(
             GetInfoError,
-            "Error getting application list",
+            "Error getting requested module",
         ))
     }
 
-    fn increase_app_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut app_ref = self
-            .get_app_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = app_ref
-            .volume
-            .increase(new_vol)
-            .expect("Volume couldn't be set");
-        let op = self
-            .handler
-            .introspect
-            .set_source_output_volume(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
-    }
-
-    fn decrease_app_volume_by_percent(&mut self, index: u32, delta: f64) {
-        let mut app_ref = self
-            .get_app_by_index(index)
-            .expect("Could not find device specified");
-        let new_vol = Volume::from(Volume(volume_from_percent(delta) as u32));
-        println!("{:?}", new_vol.print_verbose(true));
-        let volumes = app_ref
-            .volume
-            .decrease(new_vol)
-            .expect("Volume couldn't be set");
-        let op = self
-            .handler
+    /// Load module `name` with `argument`, e.g. `"sink_name=music"`, returning its index
+    pub fn load_module(&mut self, name: &str, argument: &str) -> Result<u32, ControllerError> {
+        let _span = trace::operation("load_module", None, Some(name));
+        let index = Rc::new(RefCell::new(INVALID_INDEX));
+        let index_ref = index.clone();
+        let mut handler = self.handler.borrow_handler();
+        let op = handler
             .introspect
-            .set_source_output_volume(index, &volumes, None);
-        self.handler.wait_for_operation(op).expect("error");
-    }
-
-    fn move_app_by_index(
-        &mut self,
-        stream_index: u32,
-        device_index: u32,
-    ) -> Result<bool, ControllerError> {
-        let success = Rc::new(RefCell::new(false));
-        let success_ref = success.clone();
-        let op = self.handler.introspect.move_source_output_by_index(
-            stream_index,
-            device_index,
-            Some(Box::new(move |res| {
-                success_ref.borrow_mut().clone_from(&res)
-            })),
-        );
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
-        Ok(result)
-    }
-
-    fn move_app_by_name(
-        &mut self,
-        stream_index: u32,
-        device_name: &str,
-    ) -> Result<bool, ControllerError> {
-        let success = Rc::new(RefCell::new(false));
-        let success_ref = success.clone();
-        let op = self.handler.introspect.move_source_output_by_name(
-            stream_index,
-            device_name,
-            Some(Box::new(move |res| {
-                success_ref.borrow_mut().clone_from(&res)
-            })),
-        );
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
+            .load_module(name, argument, move |res| *index_ref.borrow_mut() = res);
+        handler.wait_for_operation(op)?;
+        let result = *index.borrow();
+        if result == INVALID_INDEX {
+            return Err(ControllerError::new(
+                GetInfoError,
+                &format!("The server refused to load {}", name),
+            ));
+        }
         Ok(result)
     }
 
-    fn set_app_mute(&mut self, index: u32, mute: bool) -> Result<bool, ControllerError> {
+    pub fn unload_module(&mut self, index: u32) -> Result<bool, ControllerError> {
+        let _span = trace::operation("unload_module", Some(index), None);
         let success = Rc::new(RefCell::new(false));
         let success_ref = success.clone();
-        let op = self.handler.introspect.set_source_mute_by_index(
-            index,
-            mute,
-            Some(Box::new(move |res| {
-                success_ref.borrow_mut().clone_from(&res)
-            })),
-        );
-        self.handler.wait_for_operation(op)?;
-        let result = success.borrow_mut().clone();
+        let mut handler = self.handler.borrow_handler();
+        let op = handler
+            .introspect
+            .unload_module(index, move |res| success_ref.borrow_mut().clone_from(&res));
+        handler.wait_for_operation(op)?;
+        let result = *success.borrow_mut();
         Ok(result)
     }
 }
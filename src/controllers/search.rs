@@ -0,0 +1,142 @@
+/// Finding devices by the names people actually use for them
+///
+/// A query is compared against a device's description, its name, the `device.product.name`
+/// property and the description of its active port. Exact matches rank above substrings, which
+/// rank above fuzzy matches where the query's letters merely appear in order, e.g. `hdphn` for
+/// "Headphones". Case is ignored throughout.
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+use pulse::proplist::properties;
+
+use super::types::DeviceInfo;
+
+/// How well a query matched, best first
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The whole text equals the query.
+    Exact,
+    /// The query appears somewhere in the text.
+    Substring,
+    /// The query's characters appear in the text in the same order, with gaps.
+    Fuzzy,
+}
+
+/// What part of the device matched, in the order they are preferred when ranking
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchField {
+    Description,
+    Name,
+    ProductName,
+    ActivePort,
+}
+
+/// A device found by `DeviceControl::find_devices`
+#[derive(Debug, Clone)]
+pub struct DeviceMatch<T> {
+    pub device: T,
+    pub kind: MatchKind,
+    pub field: MatchField,
+    // characters skipped inside the text for fuzzy matches, 0 otherwise
+    gaps: usize,
+}
+
+impl<T: Borrow<DeviceInfo>> DeviceMatch<T> {
+    fn rank(&self, other: &Self) -> Ordering {
+        (self.kind, self.field, self.gaps, self.device.borrow().index).cmp(&(
+            other.kind,
+            other.field,
+            other.gaps,
+            other.device.borrow().index,
+        ))
+    }
+}
+
+/// Keep the devices matching `query`, best match first
+pub fn rank_devices<T: Borrow<DeviceInfo>>(devices: Vec<T>, query: &str) -> Vec<DeviceMatch<T>> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut matches: Vec<DeviceMatch<T>> = devices
+        .into_iter()
+        .filter_map(|device| {
+            let best = candidates(device.borrow())
+                .into_iter()
+                .filter_map(|(field, text)| {
+                    compare(&query, &text).map(|(kind, gaps)| (kind, field, gaps))
+                })
+                .min();
+            best.map(|(kind, field, gaps)| DeviceMatch {
+                device,
+                kind,
+                field,
+                gaps,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| a.rank(b));
+    matches
+}
+
+// the texts a device can be found by
+fn candidates(device: &DeviceInfo) -> Vec<(MatchField, String)> {
+    let mut texts = Vec::new();
+    if let Some(description) = &device.description {
+        texts.push((MatchField::Description, description.clone()));
+    }
+    if let Some(name) = &device.name {
+        texts.push((MatchField::Name, name.clone()));
+    }
+    if let Some(product) = device.proplist.get_str(properties::DEVICE_PRODUCT_NAME) {
+        texts.push((MatchField::ProductName, product));
+    }
+    if let Some(port) = device
+        .active_port
+        .as_ref()
+        .and_then(|port| port.description.clone())
+    {
+        texts.push((MatchField::ActivePort, port));
+    }
+    texts
+}
+
+// how `query`, already lowercased, matches `text`
+fn compare(query: &str, text: &str) -> Option<(MatchKind, usize)> {
+    let text = text.to_lowercase();
+    if text == query {
+        return Some((MatchKind::Exact, 0));
+    }
+    if text.contains(query) {
+        return Some((MatchKind::Substring, 0));
+    }
+    fuzzy_gaps(query, &text).map(|gaps| (MatchKind::Fuzzy, gaps))
+}
+
+// characters of `text` skipped between the first and last matched one,
+// `None` unless every non-blank character of `query` appears in order
+fn fuzzy_gaps(query: &str, text: &str) -> Option<usize> {
+    let mut wanted = query.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut gaps = 0;
+    let mut skipped = 0;
+    let mut started = false;
+    for c in text.chars() {
+        match wanted.peek() {
+            Some(&next) if next == c => {
+                wanted.next();
+                if started {
+                    gaps += skipped;
+                }
+                started = true;
+                skipped = 0;
+            }
+            Some(_) => skipped += 1,
+            None => break,
+        }
+    }
+    if wanted.peek().is_none() {
+        Some(gaps)
+    } else {
+        None
+    }
+}
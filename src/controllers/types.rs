@@ -1,20 +1,22 @@
+use std::fmt;
+
 use pulse::{
     channelmap,
     context::introspect,
     def,
     def::PortAvailable,
-    format,
-    proplist::Proplist,
+    direction, format,
+    proplist::{properties, Proplist},
     sample,
     time::MicroSeconds,
-    volume::{ChannelVolumes, Volume},
+    volume::{ChannelVolumes, Volume, VOLUME_NORM},
 };
 
-/// These structs are direct representations of what libpulse_binding gives
-/// created to be copyable / cloneable for use in and out of callbacks
+// These structs are direct representations of what libpulse_binding gives
+// created to be copyable / cloneable for use in and out of callbacks
 
 /// This is a wrapper around SinkPortInfo and SourcePortInfo as they have the same members
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DevicePortInfo {
     /// Name of the sink.
     pub name: Option<String>,
@@ -70,6 +72,24 @@ impl<'a> From<&'a introspect::SourcePortInfo<'a>> for DevicePortInfo {
     }
 }
 
+impl fmt::Display for DevicePortInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let available = match self.available {
+            PortAvailable::Yes => "available",
+            PortAvailable::No => "not available",
+            PortAvailable::Unknown => "availability unknown",
+        };
+        write!(
+            f,
+            "{}: {} (priority: {}, {})",
+            or_na(&self.name),
+            or_na(&self.description),
+            self.priority,
+            available
+        )
+    }
+}
+
 /// This is a wrapper around SinkState and SourceState as they have the same values
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DevState {
@@ -83,7 +103,7 @@ pub enum DevState {
     Suspended = 2,
 }
 
-impl<'a> From<def::SourceState> for DevState {
+impl From<def::SourceState> for DevState {
     fn from(s: def::SourceState) -> Self {
         match s {
             def::SourceState::Idle => DevState::Idle,
@@ -94,7 +114,7 @@ impl<'a> From<def::SourceState> for DevState {
     }
 }
 
-impl<'a> From<def::SinkState> for DevState {
+impl From<def::SinkState> for DevState {
     fn from(s: def::SinkState) -> Self {
         match s {
             def::SinkState::Idle => DevState::Idle,
@@ -105,7 +125,19 @@ impl<'a> From<def::SinkState> for DevState {
     }
 }
 
-#[derive(Clone)]
+impl fmt::Display for DevState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self {
+            DevState::Invalid => "INVALID",
+            DevState::Running => "RUNNING",
+            DevState::Idle => "IDLE",
+            DevState::Suspended => "SUSPENDED",
+        };
+        write!(f, "{}", state)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct DeviceInfo {
     /// Index of the sink.
     pub index: u32,
@@ -215,7 +247,82 @@ impl<'a> From<&'a introspect::SourceInfo<'a>> for DeviceInfo {
     }
 }
 
-#[derive(Clone)]
+/// `Proplist` and `format::Info` are compared by value rather than by pointer
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+            && self.name == other.name
+            && self.description == other.description
+            && self.sample_spec == other.sample_spec
+            && self.channel_map == other.channel_map
+            && self.owner_module == other.owner_module
+            && self.volume == other.volume
+            && self.mute == other.mute
+            && self.monitor == other.monitor
+            && self.monitor_name == other.monitor_name
+            && self.latency == other.latency
+            && self.driver == other.driver
+            && self.flags == other.flags
+            && self.proplist == other.proplist
+            && self.configured_latency == other.configured_latency
+            && self.base_volume == other.base_volume
+            && self.state == other.state
+            && self.n_volume_steps == other.n_volume_steps
+            && self.card == other.card
+            && self.ports == other.ports
+            && self.active_port == other.active_port
+            && self.formats.len() == other.formats.len()
+            && self
+                .formats
+                .iter()
+                .zip(other.formats.iter())
+                .all(|(a, b)| format_eq(a, b))
+    }
+}
+
+/// Formatted similarly to a single entry of `pactl list sinks`
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Device #{}", self.index)?;
+        writeln!(f, "\tState: {}", self.state)?;
+        writeln!(f, "\tName: {}", or_na(&self.name))?;
+        writeln!(f, "\tDescription: {}", or_na(&self.description))?;
+        writeln!(f, "\tDriver: {}", or_na(&self.driver))?;
+        writeln!(f, "\tSample Specification: {}", self.sample_spec.print())?;
+        writeln!(f, "\tChannel Map: {}", self.channel_map.print())?;
+        writeln!(f, "\tOwner Module: {}", index_or_na(self.owner_module))?;
+        writeln!(f, "\tMute: {}", yes_no(self.mute))?;
+        writeln!(
+            f,
+            "\tVolume: {}",
+            self.volume.print_verbose(Some(&self.channel_map), true)
+        )?;
+        writeln!(f, "\tBase Volume: {}", self.base_volume.print_verbose(true))?;
+        writeln!(f, "\tMonitor: {}", or_na(&self.monitor_name))?;
+        writeln!(
+            f,
+            "\tLatency: {} usec, configured {} usec",
+            self.latency.0, self.configured_latency.0
+        )?;
+        writeln!(f, "\tCard: {}", index_or_na(self.card))?;
+        write_proplist(f, &self.proplist)?;
+        if !self.ports.is_empty() {
+            writeln!(f, "\tPorts:")?;
+            for port in &self.ports {
+                writeln!(f, "\t\t{}", port)?;
+            }
+            let active = self.active_port.as_ref().and_then(|p| p.name.clone());
+            writeln!(f, "\tActive Port: {}", or_na(&active))?;
+        }
+        writeln!(f, "\tFormats:")?;
+        for format in &self.formats {
+            writeln!(f, "\t\t{}", format.print())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ApplicationInfo {
     /// Index of the sink input.
     pub index: u32,
@@ -310,6 +417,244 @@ impl<'a> From<&'a introspect::SourceOutputInfo<'a>> for ApplicationInfo {
     }
 }
 
+/// `Proplist` and `format::Info` are compared by value rather than by pointer
+impl PartialEq for ApplicationInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+            && self.name == other.name
+            && self.owner_module == other.owner_module
+            && self.client == other.client
+            && self.connection_id == other.connection_id
+            && self.sample_spec == other.sample_spec
+            && self.channel_map == other.channel_map
+            && self.volume == other.volume
+            && self.buffer_usec == other.buffer_usec
+            && self.connection_usec == other.connection_usec
+            && self.resample_method == other.resample_method
+            && self.driver == other.driver
+            && self.mute == other.mute
+            && self.proplist == other.proplist
+            && self.corked == other.corked
+            && self.has_volume == other.has_volume
+            && self.volume_writable == other.volume_writable
+            && format_eq(&self.format, &other.format)
+    }
+}
+
+/// Formatted similarly to a single entry of `pactl list sink-inputs`
+impl fmt::Display for ApplicationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Application #{}", self.index)?;
+        writeln!(f, "\tName: {}", or_na(&self.name))?;
+        writeln!(f, "\tDriver: {}", or_na(&self.driver))?;
+        writeln!(f, "\tOwner Module: {}", index_or_na(self.owner_module))?;
+        writeln!(f, "\tClient: {}", index_or_na(self.client))?;
+        writeln!(f, "\tDevice: {}", self.connection_id)?;
+        writeln!(f, "\tSample Specification: {}", self.sample_spec.print())?;
+        writeln!(f, "\tChannel Map: {}", self.channel_map.print())?;
+        writeln!(f, "\tFormat: {}", self.format.print())?;
+        writeln!(f, "\tCorked: {}", yes_no(self.corked))?;
+        writeln!(f, "\tMute: {}", yes_no(self.mute))?;
+        writeln!(
+            f,
+            "\tVolume: {}",
+            self.volume.print_verbose(Some(&self.channel_map), true)
+        )?;
+        writeln!(f, "\tBuffer Latency: {} usec", self.buffer_usec.0)?;
+        writeln!(f, "\tDevice Latency: {} usec", self.connection_usec.0)?;
+        writeln!(f, "\tResample method: {}", or_na(&self.resample_method))?;
+        write_proplist(f, &self.proplist)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardProfileInfo {
+    /// Name of this profile.
+    pub name: Option<String>,
+    /// Description of this profile.
+    pub description: Option<String>,
+    /// Number of sinks this profile would create.
+    pub n_sinks: u32,
+    /// Number of sources this profile would create.
+    pub n_sources: u32,
+    /// The higher this value is, the more useful this profile is as a default.
+    pub priority: u32,
+    /// Is this profile available? If this is `false` it makes no sense to try to activate it.
+    pub available: bool,
+}
+
+impl<'a> From<&'a introspect::CardProfileInfo2<'a>> for CardProfileInfo {
+    fn from(item: &'a introspect::CardProfileInfo2<'a>) -> Self {
+        CardProfileInfo {
+            name: item.name.as_ref().map(|cow| cow.to_string()),
+            description: item.description.as_ref().map(|cow| cow.to_string()),
+            n_sinks: item.n_sinks,
+            n_sources: item.n_sources,
+            priority: item.priority,
+            available: item.available,
+        }
+    }
+}
+
+impl<'a> From<&'a Box<introspect::CardProfileInfo2<'a>>> for CardProfileInfo {
+    fn from(item: &'a Box<introspect::CardProfileInfo2<'a>>) -> Self {
+        CardProfileInfo::from(item.as_ref())
+    }
+}
+
+impl fmt::Display for CardProfileInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (sinks: {}, sources: {}, priority: {}, available: {})",
+            or_na(&self.name),
+            or_na(&self.description),
+            self.n_sinks,
+            self.n_sources,
+            self.priority,
+            yes_no(self.available)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardPortInfo {
+    /// Name of this port.
+    pub name: Option<String>,
+    /// Description of this port.
+    pub description: Option<String>,
+    /// The higher this value is, the more useful this port is as a default.
+    pub priority: u32,
+    /// Availability status of this port.
+    pub available: PortAvailable,
+    /// The direction of this port.
+    pub direction: direction::FlagSet,
+    /// Property list.
+    pub proplist: Proplist,
+    /// Latency offset of the port that gets added to the sink/source latency when the port is
+    /// active.
+    pub latency_offset: i64,
+    /// Set of available profiles.
+    pub profiles: Vec<CardProfileInfo>,
+}
+
+impl<'a> From<&'a introspect::CardPortInfo<'a>> for CardPortInfo {
+    fn from(item: &'a introspect::CardPortInfo<'a>) -> Self {
+        CardPortInfo {
+            name: item.name.as_ref().map(|cow| cow.to_string()),
+            description: item.description.as_ref().map(|cow| cow.to_string()),
+            priority: item.priority,
+            available: item.available,
+            direction: item.direction,
+            proplist: item.proplist.clone(),
+            latency_offset: item.latency_offset,
+            profiles: item.profiles.iter().map(From::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardInfo {
+    /// Index of this card.
+    pub index: u32,
+    /// Name of this card.
+    pub name: Option<String>,
+    /// Index of the owning module, or `None`.
+    pub owner_module: Option<u32>,
+    /// Driver name.
+    pub driver: Option<String>,
+    /// Property list.
+    pub proplist: Proplist,
+    /// Set of ports.
+    pub ports: Vec<CardPortInfo>,
+    /// Set of available profiles.
+    pub profiles: Vec<CardProfileInfo>,
+    /// The active profile, or `None`.
+    pub active_profile: Option<CardProfileInfo>,
+}
+
+impl<'a> From<&'a introspect::CardInfo<'a>> for CardInfo {
+    fn from(item: &'a introspect::CardInfo<'a>) -> Self {
+        CardInfo {
+            index: item.index,
+            name: item.name.as_ref().map(|cow| cow.to_string()),
+            owner_module: item.owner_module,
+            driver: item.driver.as_ref().map(|cow| cow.to_string()),
+            proplist: item.proplist.clone(),
+            ports: item.ports.iter().map(From::from).collect(),
+            profiles: item.profiles.iter().map(From::from).collect(),
+            active_profile: item.active_profile.as_ref().map(From::from),
+        }
+    }
+}
+
+/// Formatted similarly to a single entry of `pactl list cards`
+impl fmt::Display for CardInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Card #{}", self.index)?;
+        writeln!(f, "\tName: {}", or_na(&self.name))?;
+        writeln!(f, "\tDriver: {}", or_na(&self.driver))?;
+        writeln!(f, "\tOwner Module: {}", index_or_na(self.owner_module))?;
+        write_proplist(f, &self.proplist)?;
+        writeln!(f, "\tProfiles:")?;
+        for profile in &self.profiles {
+            writeln!(f, "\t\t{}", profile)?;
+        }
+        let active = self.active_profile.as_ref().and_then(|p| p.name.clone());
+        writeln!(f, "\tActive Profile: {}", or_na(&active))?;
+        writeln!(f, "\tPorts:")?;
+        for port in &self.ports {
+            writeln!(
+                f,
+                "\t\t{}: {} (priority: {}, latency offset: {} usec)",
+                or_na(&port.name),
+                or_na(&port.description),
+                port.priority,
+                port.latency_offset
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInfo {
+    /// Index of the module.
+    pub index: u32,
+    /// Name of the module.
+    pub name: Option<String>,
+    /// Argument string of the module.
+    pub argument: Option<String>,
+    /// Usage counter or `None` if invalid.
+    pub n_used: Option<u32>,
+    /// Property list.
+    pub proplist: Proplist,
+}
+
+impl<'a> From<&'a introspect::ModuleInfo<'a>> for ModuleInfo {
+    fn from(item: &'a introspect::ModuleInfo<'a>) -> Self {
+        ModuleInfo {
+            index: item.index,
+            name: item.name.as_ref().map(|cow| cow.to_string()),
+            argument: item.argument.as_ref().map(|cow| cow.to_string()),
+            n_used: item.n_used,
+            proplist: item.proplist.clone(),
+        }
+    }
+}
+
+/// Formatted similarly to a single entry of `pactl list modules`
+impl fmt::Display for ModuleInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Module #{}", self.index)?;
+        writeln!(f, "\tName: {}", or_na(&self.name))?;
+        writeln!(f, "\tArgument: {}", or_na(&self.argument))?;
+        writeln!(f, "\tUsage counter: {}", index_or_na(self.n_used))?;
+        write_proplist(f, &self.proplist)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ServerInfo {
     /// User name of the daemon process.
     pub user_name: Option<String>,
@@ -346,3 +691,273 @@ impl<'a> From<&'a introspect::ServerInfo<'a>> for ServerInfo {
         }
     }
 }
+
+/// Formatted similarly to `pactl info`
+impl fmt::Display for ServerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "User Name: {}", or_na(&self.user_name))?;
+        writeln!(f, "Host Name: {}", or_na(&self.host_name))?;
+        writeln!(f, "Server Name: {}", or_na(&self.server_name))?;
+        writeln!(f, "Server Version: {}", or_na(&self.server_version))?;
+        writeln!(
+            f,
+            "Default Sample Specification: {}",
+            self.sample_spec.print()
+        )?;
+        writeln!(f, "Default Channel Map: {}", self.channel_map.print())?;
+        writeln!(f, "Default Sink: {}", or_na(&self.default_sink_name))?;
+        writeln!(f, "Default Source: {}", or_na(&self.default_source_name))?;
+        writeln!(
+            f,
+            "Cookie: {:04x}:{:04x}",
+            self.cookie >> 16,
+            self.cookie & 0xffff
+        )
+    }
+}
+
+/// Builds a `DeviceInfo` by hand, e.g. to feed a mock controller in tests.
+/// Anything not set describes an idle stereo device at 100% volume
+/// ```
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::types::DeviceInfoBuilder;
+///
+/// fn main() {
+///     let headphones = DeviceInfoBuilder::new(1, "alsa_output.usb-headphones")
+///         .description("USB Headphones")
+///         .mute(true)
+///         .build();
+///     assert!(headphones.mute);
+/// }
+/// ```
+pub struct DeviceInfoBuilder {
+    info: DeviceInfo,
+}
+
+impl DeviceInfoBuilder {
+    pub fn new(index: u32, name: &str) -> Self {
+        let mut channel_map = channelmap::Map::default();
+        channel_map.init_stereo();
+        let mut volume = ChannelVolumes::default();
+        volume.set(2, VOLUME_NORM);
+        DeviceInfoBuilder {
+            info: DeviceInfo {
+                index,
+                name: Some(name.to_string()),
+                description: None,
+                sample_spec: stereo_spec(),
+                channel_map,
+                owner_module: None,
+                volume,
+                mute: false,
+                monitor: None,
+                monitor_name: None,
+                latency: MicroSeconds(0),
+                driver: None,
+                flags: def::sink_flags::NOFLAGS,
+                proplist: Proplist::new().unwrap(),
+                configured_latency: MicroSeconds(0),
+                base_volume: VOLUME_NORM,
+                state: DevState::Idle,
+                n_volume_steps: 0,
+                card: None,
+                ports: Vec::new(),
+                active_port: None,
+                formats: Vec::new(),
+            },
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.info.description = Some(description.to_string());
+        self
+    }
+
+    pub fn volume(mut self, volume: ChannelVolumes) -> Self {
+        self.info.volume = volume;
+        self
+    }
+
+    pub fn mute(mut self, mute: bool) -> Self {
+        self.info.mute = mute;
+        self
+    }
+
+    pub fn state(mut self, state: DevState) -> Self {
+        self.info.state = state;
+        self
+    }
+
+    pub fn card(mut self, card: u32) -> Self {
+        self.info.card = Some(card);
+        self
+    }
+
+    pub fn monitor(mut self, index: u32, name: &str) -> Self {
+        self.info.monitor = Some(index);
+        self.info.monitor_name = Some(name.to_string());
+        self
+    }
+
+    /// Add a port, the first one added becomes the active port
+    pub fn port(mut self, port: DevicePortInfo) -> Self {
+        if self.info.active_port.is_none() {
+            self.info.active_port = Some(port.clone());
+        }
+        self.info.ports.push(port);
+        self
+    }
+
+    /// Make the port with this name active, it has to be added with `port` first
+    pub fn active_port(mut self, name: &str) -> Self {
+        self.info.active_port = self
+            .info
+            .ports
+            .iter()
+            .find(|p| p.name.as_deref() == Some(name))
+            .cloned();
+        self
+    }
+
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.info.proplist.set_str(key, value).unwrap();
+        self
+    }
+
+    pub fn build(self) -> DeviceInfo {
+        self.info
+    }
+}
+
+/// Builds an `ApplicationInfo` by hand, e.g. to feed a mock controller in tests.
+/// Anything not set describes a stereo PCM stream at 100% volume connected to device 0
+/// ```
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::types::ApplicationInfoBuilder;
+///
+/// fn main() {
+///     let player = ApplicationInfoBuilder::new(7, "Playback")
+///         .application("Music Player")
+///         .connection_id(1)
+///         .build();
+///     assert_eq!(player.connection_id, 1);
+/// }
+/// ```
+pub struct ApplicationInfoBuilder {
+    info: ApplicationInfo,
+}
+
+impl ApplicationInfoBuilder {
+    /// `name` is the stream name, set the application with `application`
+    pub fn new(index: u32, name: &str) -> Self {
+        let mut channel_map = channelmap::Map::default();
+        channel_map.init_stereo();
+        let mut volume = ChannelVolumes::default();
+        volume.set(2, VOLUME_NORM);
+        let mut format = format::Info::new().unwrap();
+        format.set_encoding(format::Encoding::PCM);
+        ApplicationInfoBuilder {
+            info: ApplicationInfo {
+                index,
+                name: Some(name.to_string()),
+                owner_module: None,
+                client: None,
+                connection_id: 0,
+                sample_spec: stereo_spec(),
+                channel_map,
+                volume,
+                buffer_usec: MicroSeconds(0),
+                connection_usec: MicroSeconds(0),
+                resample_method: None,
+                driver: None,
+                mute: false,
+                proplist: Proplist::new().unwrap(),
+                corked: false,
+                has_volume: true,
+                volume_writable: true,
+                format,
+            },
+        }
+    }
+
+    /// Sets the `application.name` property
+    pub fn application(self, name: &str) -> Self {
+        self.property(properties::APPLICATION_NAME, name)
+    }
+
+    /// Index of the device the stream is connected to
+    pub fn connection_id(mut self, index: u32) -> Self {
+        self.info.connection_id = index;
+        self
+    }
+
+    pub fn client(mut self, client: u32) -> Self {
+        self.info.client = Some(client);
+        self
+    }
+
+    pub fn volume(mut self, volume: ChannelVolumes) -> Self {
+        self.info.volume = volume;
+        self
+    }
+
+    pub fn mute(mut self, mute: bool) -> Self {
+        self.info.mute = mute;
+        self
+    }
+
+    pub fn corked(mut self, corked: bool) -> Self {
+        self.info.corked = corked;
+        self
+    }
+
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.info.proplist.set_str(key, value).unwrap();
+        self
+    }
+
+    pub fn build(self) -> ApplicationInfo {
+        self.info
+    }
+}
+
+fn stereo_spec() -> sample::Spec {
+    sample::Spec {
+        format: sample::Format::S16le,
+        rate: 44100,
+        channels: 2,
+    }
+}
+
+fn format_eq(a: &format::Info, b: &format::Info) -> bool {
+    a.get_encoding() == b.get_encoding() && a.get_properties() == b.get_properties()
+}
+
+fn or_na(value: &Option<String>) -> &str {
+    value.as_ref().map_or("n/a", String::as_str)
+}
+
+fn index_or_na(index: Option<u32>) -> String {
+    index.map_or_else(|| "n/a".to_string(), |i| i.to_string())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn write_proplist(f: &mut fmt::Formatter, proplist: &Proplist) -> fmt::Result {
+    writeln!(f, "\tProperties:")?;
+    for key in proplist.iter() {
+        match proplist.get_str(&key) {
+            Some(value) => writeln!(f, "\t\t{} = \"{}\"", key, value)?,
+            None => writeln!(f, "\t\t{} = (binary data)", key)?,
+        }
+    }
+    Ok(())
+}
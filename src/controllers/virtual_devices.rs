@@ -0,0 +1,243 @@
+/// Null sinks, loopbacks, combined and remapped sinks that live as long as their handle
+///
+/// Each constructor loads one module and returns a `VirtualDevice` that unloads it again when
+/// dropped, so a routing graph built for a session disappears with it. Call `into_module` to
+/// leave a device in place after the handle is gone. The constructors need a
+/// `ModuleController` from `PulseCtl::modules`, whose connection the handles keep a share of.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use std::time::Duration;
+///
+/// use pulsectl::PulseCtl;
+///
+/// fn main() {
+///     let pulse = PulseCtl::connect("routing").unwrap();
+///     let mut modules = pulse.modules();
+///     let stream = modules.create_null_sink("stream", "Stream mix", None).unwrap();
+///     let monitor = format!("{}.monitor", stream.sink_name().unwrap());
+///     let _listen = modules
+///         .create_loopback(&monitor, "alsa_output.usb-headphones", Duration::from_millis(20))
+///         .unwrap();
+///     // ...both are unloaded again here
+/// }
+/// ```
+use std::time::Duration;
+
+use pulse::channelmap;
+
+use super::errors::{ControllerError, ControllerErrorType::*};
+use super::types::DeviceInfo;
+use super::{Capture, DeviceControl, DeviceController, ModuleController, Playback};
+use crate::SharedHandler;
+
+/// A module loaded to provide a device, unloaded when the handle is dropped
+///
+/// Dropping can't report errors, so the module stays loaded when the connection is gone or the
+/// handler is borrowed elsewhere at that moment, e.g. by a controller mid-call. Use `destroy`
+/// to find out whether the module was unloaded.
+pub struct VirtualDevice {
+    modules: ModuleController<SharedHandler>,
+    module: Option<u32>,
+    sink: Option<String>,
+    source: Option<String>,
+}
+
+impl VirtualDevice {
+    /// Index of the module providing the device, `None` once it was unloaded
+    pub fn module(&self) -> Option<u32> {
+        self.module
+    }
+
+    /// Name of the sink the module created, if any
+    pub fn sink_name(&self) -> Option<&str> {
+        self.sink.as_deref()
+    }
+
+    /// Name of the source the module created, not counting monitors
+    pub fn source_name(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Current state of the sink the module created
+    pub fn sink(&mut self) -> Result<DeviceInfo, ControllerError> {
+        let name = self.sink.as_ref().ok_or_else(|| {
+            ControllerError::new(GetInfoError, "The module did not create a sink")
+        })?;
+        DeviceController::<Playback, _>::from_handler(self.modules.handler.clone())
+            .get_device_by_name(name)
+    }
+
+    /// Current state of the source the module created
+    pub fn source(&mut self) -> Result<DeviceInfo, ControllerError> {
+        let name = self.source.as_ref().ok_or_else(|| {
+            ControllerError::new(GetInfoError, "The module did not create a source")
+        })?;
+        DeviceController::<Capture, _>::from_handler(self.modules.handler.clone())
+            .get_device_by_name(name)
+    }
+
+    /// Unload the module now rather than when the handle is dropped
+    pub fn destroy(mut self) -> Result<bool, ControllerError> {
+        self.unload()
+    }
+
+    /// Keep the module loaded after the handle is gone, returns its index
+    pub fn into_module(mut self) -> u32 {
+        self.module.take().unwrap()
+    }
+
+    // unload the module, leaving a handle that no longer refers to anything
+    pub(crate) fn unload(&mut self) -> Result<bool, ControllerError> {
+        let module = match self.module.take() {
+            Some(module) => module,
+            None => return Ok(false),
+        };
+        if !self.connected() {
+            return Err(ControllerError::new(
+                PulseCtlError,
+                "Lost the connection to the server",
+            ));
+        }
+        self.modules.unload_module(module)
+    }
+
+    // a server that restarted has forgotten the module, and requests on a dead context panic
+    fn connected(&self) -> bool {
+        match self.modules.handler.try_borrow() {
+            Ok(handler) => handler.context.borrow().get_state() == pulse::context::State::Ready,
+            Err(_) => false,
+        }
+    }
+}
+
+impl Drop for VirtualDevice {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            if self.connected() {
+                let _ = self.modules.unload_module(module);
+            }
+        }
+    }
+}
+
+impl ModuleController<SharedHandler> {
+    /// Load module `name` with `argument` and hand it out as a `VirtualDevice`, for modules
+    /// without a constructor of their own
+    pub fn load_virtual_device(
+        &mut self,
+        name: &str,
+        argument: &str,
+    ) -> Result<VirtualDevice, ControllerError> {
+        let module = self.load_module(name, argument)?;
+        let mut device = VirtualDevice {
+            modules: ModuleController::from_handler(self.handler.clone()),
+            module: Some(module),
+            sink: None,
+            source: None,
+        };
+        let owned = |d: &DeviceInfo| d.owner_module == Some(module);
+        let sinks = DeviceController::<Playback, _>::from_handler(&mut self.handler).list_devices();
+        device.sink = sinks?.into_iter().find(owned).and_then(|d| d.name);
+        let sources =
+            DeviceController::<Capture, _>::from_handler(&mut self.handler).list_devices();
+        device.source = sources?
+            .into_iter()
+            .find(|d| owned(d) && d.monitor.is_none())
+            .and_then(|d| d.name);
+        Ok(device)
+    }
+
+    /// A sink that discards everything played to it, its monitor source carries the mix.
+    /// `channel_map` defaults to the server's usual stereo
+    pub fn create_null_sink(
+        &mut self,
+        name: &str,
+        description: &str,
+        channel_map: Option<&channelmap::Map>,
+    ) -> Result<VirtualDevice, ControllerError> {
+        let mut args = vec![
+            arg("sink_name", name),
+            arg(
+                "sink_properties",
+                &properties(&[("device.description", description)]),
+            ),
+        ];
+        args.extend(channel_map.map(channel_args).unwrap_or_default());
+        self.load_virtual_device("module-null-sink", &args.join(" "))
+    }
+
+    /// Play what `source` records on `sink`, keeping `latency` of audio in between
+    pub fn create_loopback(
+        &mut self,
+        source: &str,
+        sink: &str,
+        latency: Duration,
+    ) -> Result<VirtualDevice, ControllerError> {
+        let args = [
+            arg("source", source),
+            arg("sink", sink),
+            arg("latency_msec", &latency.as_millis().to_string()),
+        ];
+        self.load_virtual_device("module-loopback", &args.join(" "))
+    }
+
+    /// A sink that plays everything on all of `sinks` at once, which must not be empty
+    pub fn create_combined_sink(
+        &mut self,
+        sinks: &[&str],
+    ) -> Result<VirtualDevice, ControllerError> {
+        // the module combines every sink there is when given none
+        if sinks.is_empty() {
+            return Err(ControllerError::new(
+                PulseCtlError,
+                "A combined sink needs at least one sink to play on",
+            ));
+        }
+        let args = arg("slaves", &sinks.join(","));
+        self.load_virtual_device("module-combine-sink", &args)
+    }
+
+    /// A sink in front of `master` that presents its channels as `channel_map`
+    pub fn create_remap_sink(
+        &mut self,
+        master: &str,
+        channel_map: &channelmap::Map,
+    ) -> Result<VirtualDevice, ControllerError> {
+        let mut args = vec![arg("master", master)];
+        args.extend(channel_args(channel_map));
+        self.load_virtual_device("module-remap-sink", &args.join(" "))
+    }
+}
+
+/// `key='value'` for a module argument, quoted so values may contain spaces
+pub(crate) fn arg(key: &str, value: &str) -> String {
+    format!("{}='{}'", key, escape(value, '\''))
+}
+
+/// A property list in the text form `sink_properties` and friends take
+pub(crate) fn properties(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape(value, '"')))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn channel_args(channel_map: &channelmap::Map) -> Vec<String> {
+    vec![
+        arg("channels", &channel_map.len().to_string()),
+        arg("channel_map", &channel_map.print()),
+    ]
+}
+
+pub(crate) fn escape(value: &str, quote: char) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c == quote {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,322 @@
+/// Lower every other playback stream while a call is active
+///
+/// A stream counts as a call when its `media.role` property is one of the configured roles
+/// (`phone` and `communication` by default). When the first call appears every other stream is
+/// faded down by the configured amount, and faded back to its previous volume once the last call
+/// ends. Streams that start or stop in the middle of a fade are picked up or dropped as they go.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use std::time::Duration;
+///
+/// use pulsectl::controllers::SinkController;
+/// use pulsectl::ducking::Ducker;
+///
+/// fn main() {
+///     let mut ducker = Ducker::new(SinkController::create(), 0.7).ramp(Duration::from_millis(500));
+///     ducker.run().expect("lost connection to PulseAudio");
+/// }
+/// ```
+use std::collections::{HashMap, HashSet};
+use std::thread;
+use std::time::Duration;
+
+use pulse::{
+    proplist::properties,
+    volume::{ChannelVolumes, Volume},
+};
+
+use crate::controllers::{
+    errors::ControllerError, types::ApplicationInfo, AppControl, SinkController,
+};
+use crate::events::{subscription_masks, Event, Facility};
+
+// how often the volume is updated while ramping
+const STEP: Duration = Duration::from_millis(20);
+
+/// Ducks the streams of `controller`, any `AppControl` works for driving it by hand but
+/// `run` needs a `SinkController`
+pub struct Ducker<C = SinkController> {
+    pub controller: C,
+    roles: Vec<String>,
+    amount: f64,
+    ramp: Duration,
+    calls: HashSet<u32>,
+    // volume each ducked stream had before we touched it
+    ducked: HashMap<u32, ChannelVolumes>,
+    // fraction the ducked streams are currently lowered by
+    level: f64,
+}
+
+impl<C> Ducker<C> {
+    /// `amount` is the fraction other streams are lowered by, 0.5 halves their volume
+    pub fn new(controller: C, amount: f64) -> Self {
+        Ducker {
+            controller,
+            roles: vec!["phone".to_string(), "communication".to_string()],
+            amount: amount.clamp(0.0, 1.0),
+            ramp: Duration::from_millis(300),
+            calls: HashSet::new(),
+            ducked: HashMap::new(),
+            level: 0.0,
+        }
+    }
+
+    /// How long fading down or back up takes, zero changes volumes at once
+    pub fn ramp(mut self, ramp: Duration) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// The `media.role` values that trigger ducking
+    pub fn roles(mut self, roles: &[&str]) -> Self {
+        self.roles = roles.iter().map(|r| r.to_string()).collect();
+        self
+    }
+
+    /// Whether a call is currently holding the other streams down
+    pub fn is_ducking(&self) -> bool {
+        !self.calls.is_empty()
+    }
+}
+
+impl Ducker<SinkController> {
+    /// Watch playback streams and duck until an error occurs
+    pub fn run(&mut self) -> Result<(), ControllerError> {
+        self.controller
+            .handler
+            .subscribe(subscription_masks::SINK_INPUT)?;
+        for app in self.controller.list_applications()? {
+            self.stream_added(&app)?;
+        }
+        loop {
+            if !self.is_ramping() {
+                let event = self.controller.handler.next_event()?;
+                self.handle_event(event)?;
+                continue;
+            }
+            while let Some(event) = self.controller.handler.try_next_event()? {
+                self.handle_event(event)?;
+            }
+            self.step()?;
+            if self.is_ramping() {
+                thread::sleep(STEP);
+            }
+        }
+    }
+}
+
+impl<C: AppControl<ApplicationInfo>> Ducker<C> {
+    /// React to a single subscription event, for callers that run their own event loop.
+    /// Ramps are not advanced here, call `step` periodically while `is_ramping` is true
+    pub fn handle_event(&mut self, event: Event) -> Result<(), ControllerError> {
+        if event == Event::Reconnected {
+            // the server restarted, streams and indexes we knew about are gone
+            self.calls.clear();
+            self.ducked.clear();
+            self.level = 0.0;
+            for app in self.controller.list_applications()? {
+                self.stream_added(&app)?;
+            }
+            return Ok(());
+        }
+        if event.facility() != Some(Facility::SinkInput) {
+            return Ok(());
+        }
+        match event {
+            Event::New(_, index) => {
+                if let Ok(app) = self.controller.get_app_by_index(index) {
+                    self.stream_added(&app)?;
+                }
+            }
+            Event::Removed(_, index) => {
+                self.calls.remove(&index);
+                self.ducked.remove(&index);
+            }
+            Event::Changed(..) | Event::Reconnected => {}
+        }
+        Ok(())
+    }
+
+    /// Whether the ducked streams are still on their way down or back up
+    pub fn is_ramping(&self) -> bool {
+        (self.level - self.target()).abs() >= f64::EPSILON
+    }
+
+    /// Move the duck level one step closer to where it should be
+    pub fn step(&mut self) -> Result<(), ControllerError> {
+        let target = self.target();
+        if self.level == 0.0 && target > 0.0 {
+            // starting to duck, remember where everything was
+            for app in self.controller.list_applications()? {
+                if !self.calls.contains(&app.index) {
+                    self.ducked.entry(app.index).or_insert(app.volume);
+                }
+            }
+        }
+        let steps = (self.ramp.as_secs_f64() / STEP.as_secs_f64()).max(1.0);
+        let delta = self.amount / steps;
+        self.level = if self.level < target {
+            (self.level + delta).min(target)
+        } else {
+            (self.level - delta).max(target)
+        };
+
+        let ducked: Vec<(u32, ChannelVolumes)> =
+            self.ducked.iter().map(|(i, v)| (*i, *v)).collect();
+        for (index, original) in ducked {
+            // the stream may have vanished since the last event, a failed set is not an error
+            self.controller
+                .set_app_volume(index, &scale(&original, 1.0 - self.level))?;
+        }
+        if self.level == 0.0 {
+            self.ducked.clear();
+        }
+        Ok(())
+    }
+
+    fn target(&self) -> f64 {
+        if self.calls.is_empty() {
+            0.0
+        } else {
+            self.amount
+        }
+    }
+
+    fn is_call(&self, app: &ApplicationInfo) -> bool {
+        app.proplist
+            .get_str(properties::MEDIA_ROLE)
+            .is_some_and(|role| self.roles.contains(&role))
+    }
+
+    fn stream_added(&mut self, app: &ApplicationInfo) -> Result<(), ControllerError> {
+        if self.is_call(app) {
+            self.calls.insert(app.index);
+            self.ducked.remove(&app.index);
+        } else if self.level > 0.0 {
+            // joined in the middle of a duck, bring it down to the current level right away
+            self.ducked.insert(app.index, app.volume);
+            self.controller
+                .set_app_volume(app.index, &scale(&app.volume, 1.0 - self.level))?;
+        }
+        Ok(())
+    }
+}
+
+fn scale(volume: &ChannelVolumes, factor: f64) -> ChannelVolumes {
+    let mut scaled = *volume;
+    for channel in scaled.get_mut() {
+        *channel = Volume((f64::from(channel.0) * factor) as u32);
+    }
+    scaled
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::controllers::mock::MockController;
+    use crate::controllers::types::ApplicationInfoBuilder;
+
+    fn stereo(volume: u32) -> ChannelVolumes {
+        let mut volumes = ChannelVolumes::default();
+        volumes.set(2, Volume(volume));
+        volumes
+    }
+
+    fn call(index: u32) -> ApplicationInfo {
+        ApplicationInfoBuilder::new(index, "Call")
+            .property(properties::MEDIA_ROLE, "phone")
+            .build()
+    }
+
+    fn volume_of(ducker: &Ducker<MockController>, index: u32) -> ChannelVolumes {
+        let app = ducker
+            .controller
+            .applications
+            .iter()
+            .find(|a| a.index == index);
+        app.unwrap().volume
+    }
+
+    fn music_and_ducker(ramp: Duration) -> Ducker<MockController> {
+        let music = ApplicationInfoBuilder::new(1, "Music")
+            .volume(stereo(60000))
+            .build();
+        Ducker::new(MockController::new().with_app(music), 0.5).ramp(ramp)
+    }
+
+    #[test]
+    fn calls_ramp_other_streams_down_and_back_up() {
+        // three steps of 20ms each way
+        let mut ducker = music_and_ducker(Duration::from_millis(60));
+        ducker.controller.applications.push(call(2));
+        ducker
+            .handle_event(Event::New(Facility::SinkInput, 2))
+            .unwrap();
+        assert!(ducker.is_ducking());
+
+        let mut previous = 60000;
+        for _ in 0..3 {
+            assert!(ducker.is_ramping());
+            ducker.step().unwrap();
+            let current = volume_of(&ducker, 1).get()[0].0;
+            assert!(current < previous);
+            previous = current;
+        }
+        assert!(!ducker.is_ramping());
+        assert_eq!(volume_of(&ducker, 1), stereo(30000));
+        assert_eq!(volume_of(&ducker, 2), call(2).volume);
+
+        ducker.controller.applications.retain(|a| a.index != 2);
+        ducker
+            .handle_event(Event::Removed(Facility::SinkInput, 2))
+            .unwrap();
+        assert!(!ducker.is_ducking());
+        let mut steps = 0;
+        while ducker.is_ramping() {
+            ducker.step().unwrap();
+            steps += 1;
+        }
+        assert_eq!(steps, 3);
+        assert_eq!(volume_of(&ducker, 1), stereo(60000));
+    }
+
+    #[test]
+    fn streams_joining_a_duck_are_lowered_and_restored() {
+        let mut ducker = music_and_ducker(Duration::from_millis(0));
+        ducker.controller.applications.push(call(2));
+        ducker
+            .handle_event(Event::New(Facility::SinkInput, 2))
+            .unwrap();
+        ducker.step().unwrap();
+        assert_eq!(volume_of(&ducker, 1), stereo(30000));
+
+        let late = ApplicationInfoBuilder::new(3, "Video")
+            .volume(stereo(40000))
+            .build();
+        ducker.controller.applications.push(late);
+        ducker
+            .handle_event(Event::New(Facility::SinkInput, 3))
+            .unwrap();
+        assert_eq!(volume_of(&ducker, 3), stereo(20000));
+
+        ducker
+            .handle_event(Event::Removed(Facility::SinkInput, 2))
+            .unwrap();
+        ducker.step().unwrap();
+        assert!(!ducker.is_ramping());
+        assert_eq!(volume_of(&ducker, 1), stereo(60000));
+        assert_eq!(volume_of(&ducker, 3), stereo(40000));
+    }
+
+    #[test]
+    fn only_configured_roles_count_as_calls() {
+        let mut ducker = music_and_ducker(Duration::from_millis(0)).roles(&["game"]);
+        ducker.controller.applications.push(call(2));
+        ducker
+            .handle_event(Event::New(Facility::SinkInput, 2))
+            .unwrap();
+        assert!(!ducker.is_ducking());
+        assert!(!ducker.is_ramping());
+    }
+}
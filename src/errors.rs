@@ -25,21 +25,29 @@ impl fmt::Debug for PulseCtlError {
             PulseCtlErrorType::PulseAudioError => {
                 error_string.push_str("PulseAudioError");
             }
+            PulseCtlErrorType::TimeoutError => {
+                error_string.push_str("TimeoutError");
+            }
         }
         write!(f, "[{}]: {}", error_string, self.message)
     }
 }
 
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone)]
 pub(crate) enum PulseCtlErrorType {
     ConnectError,
     OperationError,
     PulseAudioError,
+    TimeoutError,
 }
 
-/// Error thrown when PulseAudio throws an error code, there are 3 variants
+/// Error thrown when PulseAudio throws an error code, there are 4 variants
 /// `PulseCtlErrorType::ConnectError` when there's an error establishing a connection
 /// `PulseCtlErrorType::OperationError` when the requested operation quis unexpecdatly or is cancelled
 /// `PulseCtlErrorType::PulseAudioError` when PulseAudio returns an error code in any circumstance
+/// `PulseCtlErrorType::TimeoutError` when the server did not answer before the handler's deadline
+#[derive(Clone)]
 pub struct PulseCtlError {
     error: PulseCtlErrorType,
     message: String,
@@ -52,4 +60,9 @@ impl PulseCtlError {
             message: msg.to_string(),
         }
     }
+
+    /// Whether the server failed to answer in time, see `HandlerBuilder::timeout`
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.error, PulseCtlErrorType::TimeoutError)
+    }
 }
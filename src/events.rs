@@ -0,0 +1,49 @@
+/// Change notifications delivered by PulseAudio once a `Handler` has subscribed to them
+///
+/// Subscribing only tells you *what* changed, the new state of the object has to be fetched
+/// again through one of the controllers.
+pub use pulse::context::subscribe::{subscription_masks, Facility, InterestMaskSet};
+
+use pulse::context::subscribe::Operation;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A new object appeared on the server
+    New(Facility, u32),
+    /// A property of an existing object was modified
+    Changed(Facility, u32),
+    /// An object was removed from the server
+    Removed(Facility, u32),
+    /// The handler lost its connection and reconnected, every index seen before may be stale
+    Reconnected,
+}
+
+impl Event {
+    pub(crate) fn new(facility: Facility, operation: Operation, index: u32) -> Self {
+        match operation {
+            Operation::New => Event::New(facility, index),
+            Operation::Changed => Event::Changed(facility, index),
+            Operation::Removed => Event::Removed(facility, index),
+        }
+    }
+
+    /// The kind of object this event refers to, `None` for `Reconnected`
+    pub fn facility(&self) -> Option<Facility> {
+        match *self {
+            Event::New(facility, _) | Event::Changed(facility, _) | Event::Removed(facility, _) => {
+                Some(facility)
+            }
+            Event::Reconnected => None,
+        }
+    }
+
+    /// The index of the object this event refers to, `None` for `Reconnected`
+    pub fn index(&self) -> Option<u32> {
+        match *self {
+            Event::New(_, index) | Event::Changed(_, index) | Event::Removed(_, index) => {
+                Some(index)
+            }
+            Event::Reconnected => None,
+        }
+    }
+}
@@ -0,0 +1,383 @@
+/// Smooth volume changes that run on the `Handler` mainloop
+///
+/// Starting a fade returns immediately, the volume is then stepped towards the target every time
+/// the mainloop runs, which happens during any other call on the same controller or explicitly
+/// through `Handler::wait_for_fade`. A fade stops early when a new fade is started for the same
+/// device or stream, or when something else changes its volume in the meantime.
+/// ```no_run
+/// extern crate libpulse_binding as pulse;
+/// extern crate pulsectl;
+///
+/// use std::time::Duration;
+///
+/// use pulse::volume::Volume;
+/// use pulsectl::controllers::{DeviceControl, SinkController};
+/// use pulsectl::fade::FadeCurve;
+///
+/// fn main() {
+///     let mut handler = SinkController::create();
+///     let device = handler.get_default_device().unwrap();
+///     let mut silent = device.volume;
+///     silent.set(u32::from(silent.len()), Volume(0));
+///     let fade = handler
+///         .fade_device_volume(device.index, &silent, Duration::from_secs(2), FadeCurve::Logarithmic)
+///         .unwrap();
+///     handler.handler.wait_for_fade(&fade).unwrap();
+/// }
+/// ```
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use pulse::{
+    callbacks::ListResult,
+    context::{introspect::Introspector, subscribe::Facility, Context},
+    mainloop::{api::Mainloop as MainloopTrait, events::timer::TimeEvent, standard::Mainloop},
+    time::{MicroSeconds, MonotonicTs},
+    volume::{ChannelVolumes, Volume, VolumeDB},
+};
+
+use crate::Handler;
+
+pub(crate) type Timer = TimeEvent<<Mainloop as MainloopTrait>::MI>;
+
+// time between two volume updates
+const STEP: Duration = Duration::from_millis(20);
+// quietest level a logarithmic fade passes through on its way to or from silence
+const FLOOR_DB: f64 = -60.0;
+// how far the server may round a volume we set before we treat it as changed by someone else
+const TOLERANCE: u32 = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FadeCurve {
+    /// Moves the volume slider at a constant speed.
+    Linear,
+    /// Changes loudness at a constant rate in decibels, which sounds the most even.
+    Logarithmic,
+    /// Starts and ends slowly with the quickest change in the middle.
+    Cubic,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FadeStatus {
+    Running,
+    /// The target volume was reached.
+    Finished,
+    /// Stopped by a newer fade, an outside volume change, `Fade::cancel` or the target vanishing.
+    Cancelled,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum FadeTarget {
+    Sink(u32),
+    Source(u32),
+    SinkInput(u32),
+    SourceOutput(u32),
+}
+
+impl FadeTarget {
+    pub(crate) fn new(facility: Facility, index: u32) -> Self {
+        match facility {
+            Facility::Sink => FadeTarget::Sink(index),
+            Facility::Source => FadeTarget::Source(index),
+            Facility::SinkInput => FadeTarget::SinkInput(index),
+            Facility::SourceOutput => FadeTarget::SourceOutput(index),
+            _ => unreachable!("only devices and streams have a volume to fade"),
+        }
+    }
+}
+
+/// Handle to a fade that was started on a controller
+#[derive(Clone)]
+pub struct Fade {
+    state: Rc<RefCell<FadeState>>,
+}
+
+impl Fade {
+    pub fn status(&self) -> FadeStatus {
+        self.state.borrow().status
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status() != FadeStatus::Running
+    }
+
+    /// Stop the fade, leaving the volume wherever it currently is
+    pub fn cancel(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.status == FadeStatus::Running {
+            state.status = FadeStatus::Cancelled;
+        }
+    }
+}
+
+struct FadeState {
+    target: FadeTarget,
+    from: ChannelVolumes,
+    to: ChannelVolumes,
+    curve: FadeCurve,
+    started: Instant,
+    duration: Duration,
+    last_set: Option<ChannelVolumes>,
+    // a volume query is in flight, skip ticks until it is answered
+    pending: bool,
+    status: FadeStatus,
+}
+
+impl FadeState {
+    fn volume_at(&self, progress: f64) -> ChannelVolumes {
+        let mut volume = self.from;
+        for (i, channel) in volume.get_mut().iter_mut().enumerate() {
+            let to = self
+                .to
+                .get()
+                .get(i)
+                .copied()
+                .unwrap_or_else(|| self.to.avg());
+            *channel = interpolate(self.curve, *channel, to, progress);
+        }
+        volume
+    }
+
+    // called with the volume the server reports right before each step
+    fn advance(&mut self, current: ChannelVolumes, introspect: &mut Introspector) {
+        self.pending = false;
+        if self.status != FadeStatus::Running {
+            return;
+        }
+        if let Some(last) = self.last_set {
+            if !roughly_equal(&current, &last) {
+                self.status = FadeStatus::Cancelled;
+                return;
+            }
+        }
+        let progress = if self.duration.as_secs_f64() > 0.0 {
+            (self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+        } else {
+            1.0
+        };
+        let volume = self.volume_at(progress);
+        set_volume(introspect, self.target, &volume);
+        self.last_set = Some(volume);
+        if progress >= 1.0 {
+            self.status = FadeStatus::Finished;
+        }
+    }
+}
+
+/// Register a fade on the handler's mainloop, replacing any fade already running on `target`
+pub(crate) fn start(
+    handler: &mut Handler,
+    target: FadeTarget,
+    from: ChannelVolumes,
+    to: ChannelVolumes,
+    duration: Duration,
+    curve: FadeCurve,
+) -> Fade {
+    handler.fades.retain(|_, (fade, _)| !fade.is_done());
+    if let Some((previous, _)) = handler.fades.remove(&target) {
+        previous.cancel();
+    }
+
+    let state = Rc::new(RefCell::new(FadeState {
+        target,
+        from,
+        to,
+        curve,
+        started: Instant::now(),
+        duration,
+        last_set: None,
+        pending: false,
+        status: FadeStatus::Running,
+    }));
+    let fade = Fade {
+        state: state.clone(),
+    };
+
+    let context = handler.context.clone();
+    let timer = handler
+        .mainloop
+        .borrow_mut()
+        .new_timer_event_rt(
+            MonotonicTs::now(),
+            Box::new(move |mut event| {
+                if state.borrow().status != FadeStatus::Running {
+                    return;
+                }
+                if !state.borrow().pending {
+                    state.borrow_mut().pending = true;
+                    query_volume(&context, state.clone());
+                }
+                event.restart_rt(MonotonicTs::now() + MicroSeconds(STEP.as_micros() as u64));
+            }),
+        )
+        .expect("Failed to create fade timer");
+    handler.fades.insert(target, (fade.clone(), timer));
+    fade
+}
+
+fn query_volume(context: &Rc<RefCell<Context>>, state: Rc<RefCell<FadeState>>) {
+    // requests on a dead context are refused outright, the handler will reconnect or fail
+    if context.borrow().get_state() != pulse::context::State::Ready {
+        let mut state = state.borrow_mut();
+        state.pending = false;
+        state.status = FadeStatus::Cancelled;
+        return;
+    }
+    let introspect = context.borrow().introspect();
+    let target = state.borrow().target;
+    let context = context.clone();
+    let on_result = move |result: ListResult<ChannelVolumes>| {
+        let mut state = state.borrow_mut();
+        match result {
+            ListResult::Item(volume) => {
+                state.advance(volume, &mut context.borrow().introspect());
+            }
+            ListResult::Error => {
+                state.pending = false;
+                if state.status == FadeStatus::Running {
+                    state.status = FadeStatus::Cancelled;
+                }
+            }
+            ListResult::End => {}
+        }
+    };
+    // the operations are dropped right away, their callbacks still run once the answer arrives
+    match target {
+        FadeTarget::Sink(index) => {
+            introspect
+                .get_sink_info_by_index(index, move |res| on_result(map_item(res, |i| i.volume)));
+        }
+        FadeTarget::Source(index) => {
+            introspect
+                .get_source_info_by_index(index, move |res| on_result(map_item(res, |i| i.volume)));
+        }
+        FadeTarget::SinkInput(index) => {
+            introspect
+                .get_sink_input_info(index, move |res| on_result(map_item(res, |i| i.volume)));
+        }
+        FadeTarget::SourceOutput(index) => {
+            introspect
+                .get_source_output_info(index, move |res| on_result(map_item(res, |i| i.volume)));
+        }
+    }
+}
+
+fn map_item<T, F: Fn(&T) -> ChannelVolumes>(
+    result: ListResult<&T>,
+    volume: F,
+) -> ListResult<ChannelVolumes> {
+    match result {
+        ListResult::Item(item) => ListResult::Item(volume(item)),
+        ListResult::End => ListResult::End,
+        ListResult::Error => ListResult::Error,
+    }
+}
+
+fn set_volume(introspect: &mut Introspector, target: FadeTarget, volume: &ChannelVolumes) {
+    match target {
+        FadeTarget::Sink(index) => {
+            introspect.set_sink_volume_by_index(index, volume, None);
+        }
+        FadeTarget::Source(index) => {
+            introspect.set_source_volume_by_index(index, volume, None);
+        }
+        FadeTarget::SinkInput(index) => {
+            introspect.set_sink_input_volume(index, volume, None);
+        }
+        FadeTarget::SourceOutput(index) => {
+            introspect.set_source_output_volume(index, volume, None);
+        }
+    }
+}
+
+fn interpolate(curve: FadeCurve, from: Volume, to: Volume, progress: f64) -> Volume {
+    let linear =
+        |t: f64| Volume((f64::from(from.0) + (f64::from(to.0) - f64::from(from.0)) * t) as u32);
+    match curve {
+        FadeCurve::Linear => linear(progress),
+        FadeCurve::Cubic => {
+            let t = if progress < 0.5 {
+                4.0 * progress.powi(3)
+            } else {
+                1.0 - (-2.0 * progress + 2.0).powi(3) / 2.0
+            };
+            linear(t)
+        }
+        FadeCurve::Logarithmic => {
+            if progress >= 1.0 {
+                return to;
+            }
+            let from_db = VolumeDB::from(from).0.max(FLOOR_DB);
+            let to_db = VolumeDB::from(to).0.max(FLOOR_DB);
+            Volume::from(VolumeDB(from_db + (to_db - from_db) * progress))
+        }
+    }
+}
+
+fn roughly_equal(a: &ChannelVolumes, b: &ChannelVolumes) -> bool {
+    a.len() == b.len()
+        && a.get()
+            .iter()
+            .zip(b.get().iter())
+            .all(|(x, y)| x.0.max(y.0) - x.0.min(y.0) <= TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulse::volume::{VOLUME_MUTED, VOLUME_NORM};
+
+    fn stereo(left: u32, right: u32) -> ChannelVolumes {
+        let mut volumes = ChannelVolumes::default();
+        volumes.set(2, Volume(left));
+        volumes.get_mut()[1] = Volume(right);
+        volumes
+    }
+
+    #[test]
+    fn every_curve_starts_and_ends_on_its_endpoints() {
+        let (from, to) = (Volume(10000), Volume(60000));
+        for curve in &[FadeCurve::Linear, FadeCurve::Cubic, FadeCurve::Logarithmic] {
+            let start = interpolate(*curve, from, to, 0.0);
+            assert!(start.0.max(from.0) - start.0.min(from.0) <= TOLERANCE);
+            assert_eq!(interpolate(*curve, from, to, 1.0), to);
+        }
+    }
+
+    #[test]
+    fn linear_and_cubic_meet_halfway() {
+        let (from, to) = (Volume(0), Volume(60000));
+        assert_eq!(interpolate(FadeCurve::Linear, from, to, 0.5), Volume(30000));
+        assert_eq!(interpolate(FadeCurve::Cubic, from, to, 0.5), Volume(30000));
+        // eased at the edges
+        assert!(interpolate(FadeCurve::Cubic, from, to, 0.1).0 < 6000);
+        assert!(interpolate(FadeCurve::Cubic, from, to, 0.9).0 > 54000);
+    }
+
+    #[test]
+    fn logarithmic_fades_from_silence_through_the_floor() {
+        let middle = interpolate(FadeCurve::Logarithmic, VOLUME_MUTED, VOLUME_NORM, 0.5);
+        let expected = Volume::from(VolumeDB(FLOOR_DB / 2.0));
+        assert!(middle.0.max(expected.0) - middle.0.min(expected.0) <= 1);
+        assert!(middle.0 < VOLUME_NORM.0 / 2);
+    }
+
+    #[test]
+    fn volumes_within_the_tolerance_are_equal() {
+        let volume = stereo(30000, 40000);
+        assert!(roughly_equal(&volume, &volume));
+        assert!(roughly_equal(
+            &volume,
+            &stereo(30000 + TOLERANCE, 40000 - TOLERANCE)
+        ));
+        assert!(!roughly_equal(
+            &volume,
+            &stereo(30000, 40000 + TOLERANCE + 1)
+        ));
+
+        let mut mono = ChannelVolumes::default();
+        mono.set(1, Volume(30000));
+        assert!(!roughly_equal(&volume, &mono));
+    }
+}
@@ -0,0 +1,229 @@
+/// Keep the most preferred device that is present set as the default
+///
+/// A `FallbackChain` is given devices in order of preference, e.g. a USB DAC, then HDMI, then
+/// the laptop speakers. Whenever a device appears or disappears the highest ranked one that is
+/// present becomes the default, and the streams on the previous default follow it, also when the
+/// previous default was unplugged and the server moved its streams somewhere else. When none of
+/// the preferred devices are present the server's own choice is left alone.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::SinkController;
+/// use pulsectl::fallback::{FallbackChain, Preference};
+///
+/// fn main() {
+///     let mut chain = FallbackChain::new(
+///         SinkController::create(),
+///         vec![
+///             Preference::Property("device.serial".into(), "Vendor_DAC_0042".into()),
+///             Preference::Property("device.profile.name".into(), "hdmi-stereo".into()),
+///             Preference::Name("alsa_output.pci-0000_00_1f.3.analog-stereo".into()),
+///         ],
+///     );
+///     chain.run().expect("lost connection to PulseAudio");
+/// }
+/// ```
+use std::collections::HashSet;
+
+use crate::controllers::{
+    errors::{ControllerError, ControllerErrorType::*},
+    identity::DeviceId,
+    types::DeviceInfo,
+    AppControl, DeviceControl, DeviceController, Direction,
+};
+use crate::events::Event;
+use crate::{Handler, HandlerRef};
+
+/// One entry of the chain, telling which device it stands for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Preference {
+    /// The device with this name.
+    Name(String),
+    /// The device with this identity, see `DeviceId::matches`.
+    Id(DeviceId),
+    /// Any device whose property `key` has exactly this value.
+    Property(String, String),
+}
+
+impl Preference {
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        match self {
+            Preference::Name(name) => device.name.as_ref() == Some(name),
+            Preference::Id(id) => id.matches(device),
+            Preference::Property(key, value) => {
+                device.proplist.get_str(key).as_ref() == Some(value)
+            }
+        }
+    }
+}
+
+pub struct FallbackChain<D, H = Handler> {
+    pub controller: DeviceController<D, H>,
+    preferences: Vec<Preference>,
+    migrate: bool,
+    // the device the chain last made the default
+    applied: Option<u32>,
+    // streams on `applied`, followed wherever the server rescues them to when it disappears
+    followers: HashSet<u32>,
+}
+
+impl<D: Direction, H: HandlerRef> FallbackChain<D, H> {
+    /// `preferences` are ordered from most to least preferred
+    pub fn new(controller: DeviceController<D, H>, preferences: Vec<Preference>) -> Self {
+        FallbackChain {
+            controller,
+            preferences,
+            migrate: true,
+            applied: None,
+            followers: HashSet::new(),
+        }
+    }
+
+    /// Whether streams on the previous default are moved along to the new one, on by default
+    pub fn migrate(mut self, migrate: bool) -> Self {
+        self.migrate = migrate;
+        self
+    }
+
+    pub fn preferences(&self) -> &[Preference] {
+        &self.preferences
+    }
+
+    /// The most preferred device that is currently present
+    pub fn preferred(&mut self) -> Result<Option<DeviceInfo>, ControllerError> {
+        let devices = self.controller.list_devices()?;
+        Ok(self.pick(&devices).cloned())
+    }
+
+    /// Make the most preferred present device the default and move the streams on the previous
+    /// default over to it. Returns the device that is the default afterwards, `None` when no
+    /// preferred device is present, and an error when the server refuses the switch
+    pub fn apply(&mut self) -> Result<Option<DeviceInfo>, ControllerError> {
+        let devices = self.controller.list_devices()?;
+        let target = match self.pick(&devices) {
+            Some(target) => target.clone(),
+            None => return Ok(None),
+        };
+        let server = self.controller.get_server_info()?;
+        let current = D::default_device_name(&server);
+        let switch = current.is_none() || current != target.name;
+        if switch {
+            // `pick` only hands out named devices
+            let name = target.name.as_deref().unwrap_or_default();
+            debug!(device = %name, "switching default device");
+            if !self.controller.set_default_device(name)? {
+                return Err(ControllerError::new(
+                    PulseCtlError,
+                    &format!("The server refused to make {} the default", name),
+                ));
+            }
+        }
+        if !self.migrate {
+            self.applied = Some(target.index);
+            return Ok(Some(target));
+        }
+
+        // streams on the default we replace, and those of a default that was removed since
+        let previous = devices
+            .iter()
+            .find(|d| switch && current.is_some() && d.name == current)
+            .map(|d| d.index);
+        let lost = self
+            .applied
+            .is_some_and(|applied| devices.iter().all(|d| d.index != applied));
+        let apps = self.controller.list_applications()?;
+        let moving: Vec<u32> = apps
+            .iter()
+            .filter(|a| a.connection_id != target.index)
+            .filter(|a| {
+                Some(a.connection_id) == previous || (lost && self.followers.contains(&a.index))
+            })
+            .map(|a| a.index)
+            .collect();
+        let mut batch = self.controller.batch();
+        for &app in &moving {
+            batch = batch.move_app_by_index(app, target.index);
+        }
+        self.followers = apps
+            .iter()
+            .filter(|a| a.connection_id == target.index)
+            .map(|a| a.index)
+            .collect();
+        // streams that ended or refuse to move are left where they are
+        for (app, result) in moving.into_iter().zip(batch.run()) {
+            if result? {
+                self.followers.insert(app);
+            }
+        }
+        self.applied = Some(target.index);
+        Ok(Some(target))
+    }
+
+    /// Watch devices and streams come and go and keep the default up to date until an error
+    /// occurs
+    pub fn run(&mut self) -> Result<(), ControllerError> {
+        self.controller
+            .handler
+            .borrow_handler()
+            .subscribe(D::DEVICE.to_interest_mask() | D::APP.to_interest_mask())?;
+        self.apply()?;
+        loop {
+            let event = self.controller.handler.borrow_handler().next_event()?;
+            self.handle_event(event)?;
+        }
+    }
+
+    /// React to a single subscription event, for callers that run their own event loop.
+    /// Subscribe to the streams of the direction as well as its devices, or streams that joined
+    /// the default later stay behind when it is unplugged
+    pub fn handle_event(&mut self, event: Event) -> Result<(), ControllerError> {
+        match event {
+            Event::Reconnected => {
+                // indexes are handed out anew by the restarted server
+                self.applied = None;
+                self.followers.clear();
+                self.apply()?;
+            }
+            Event::New(facility, _) | Event::Removed(facility, _) if facility == D::DEVICE => {
+                self.apply()?;
+            }
+            Event::New(facility, index) | Event::Changed(facility, index) if facility == D::APP => {
+                self.stream_changed(index)?;
+            }
+            Event::Removed(facility, index) if facility == D::APP => {
+                self.followers.remove(&index);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // keep track of whether a stream is on the default the chain applied
+    fn stream_changed(&mut self, index: u32) -> Result<(), ControllerError> {
+        let applied = match self.applied {
+            Some(applied) if self.migrate => applied,
+            _ => return Ok(()),
+        };
+        let app = match self.controller.get_app_by_index(index) {
+            Ok(app) => app,
+            // ended in the meantime
+            Err(_) => return Ok(()),
+        };
+        if app.connection_id == applied {
+            self.followers.insert(index);
+        } else if self.controller.get_device_by_index(applied).is_ok() {
+            // moved away on purpose, a stream rescued off a removed default still follows
+            self.followers.remove(&index);
+        }
+        Ok(())
+    }
+
+    // devices without a name can't be made the default, so they never count as present
+    fn pick<'a>(&self, devices: &'a [DeviceInfo]) -> Option<&'a DeviceInfo> {
+        self.preferences.iter().find_map(|preference| {
+            devices
+                .iter()
+                .find(|d| d.name.is_some() && preference.matches(d))
+        })
+    }
+}
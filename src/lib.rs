@@ -34,69 +34,182 @@
 /// ```
 extern crate libpulse_binding as pulse;
 
-use std::cell::RefCell;
-use std::ops::Deref;
+use std::cell::{RefCell, RefMut};
+use std::collections::{HashMap, VecDeque};
+use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use pulse::{
-    context::{introspect, Context},
-    mainloop::standard::{IterateResult, Mainloop},
+    context::{introspect, subscribe::InterestMaskSet, Context, FlagSet},
+    mainloop::standard::Mainloop,
     operation::{Operation, State},
     proplist::Proplist,
 };
 
+use crate::controllers::{Capture, CardController, DeviceController, ModuleController, Playback};
 use crate::errors::{PulseCtlError, PulseCtlErrorType::*};
+use crate::events::Event;
+use crate::fade::{Fade, FadeTarget, Timer};
+
+#[macro_use]
+mod trace;
 
 pub mod controllers;
+pub mod ducking;
 mod errors;
+pub mod events;
+pub mod fade;
+pub mod fallback;
+pub mod mixer;
+pub mod rules;
+pub mod snapshot;
 
 pub struct Handler {
     pub mainloop: Rc<RefCell<Mainloop>>,
     pub context: Rc<RefCell<Context>>,
     pub introspect: introspect::Introspector,
+    events: Rc<RefCell<VecDeque<Event>>>,
+    fades: HashMap<FadeTarget, (Fade, Timer)>,
+    timeout: Option<Duration>,
+    subscription: Option<InterestMaskSet>,
+    config: HandlerBuilder,
 }
 
-impl Handler {
-    pub fn connect(name: &str) -> Result<Handler, PulseCtlError> {
-        let mut proplist = Proplist::new().unwrap();
-        proplist
-            .set_str(pulse::proplist::properties::APPLICATION_NAME, name)
-            .unwrap();
+// first and longest wait between two reconnection attempts
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
+/// Connection options for a `Handler`, for when the defaults picked by `Handler::connect`
+/// (the server from the environment, autospawn allowed, no timeout) are not what you want
+/// ```no_run
+/// extern crate libpulse_binding as pulse;
+/// extern crate pulsectl;
+///
+/// use std::time::Duration;
+///
+/// use pulsectl::controllers::SinkController;
+/// use pulsectl::HandlerBuilder;
+///
+/// fn main() {
+///     let handler = HandlerBuilder::new("remote-mixer")
+///         .server("tcp:livingroom.local")
+///         .flags(pulse::context::flags::NOAUTOSPAWN)
+///         .timeout(Duration::from_secs(5))
+///         .connect()
+///         .expect("could not reach the living room speakers");
+///     let mut sinks = SinkController::from_handler(handler);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct HandlerBuilder {
+    name: String,
+    server: Option<String>,
+    properties: Vec<(String, String)>,
+    flags: FlagSet,
+    timeout: Option<Duration>,
+    reconnect: bool,
+}
+
+impl HandlerBuilder {
+    /// `name` is reported to the server as the `application.name` of the client
+    pub fn new(name: &str) -> Self {
+        HandlerBuilder {
+            name: name.to_string(),
+            server: None,
+            properties: Vec::new(),
+            flags: pulse::context::flags::NOFLAGS,
+            timeout: None,
+            reconnect: false,
+        }
+    }
+
+    /// Server to connect to, e.g. `tcp:host`, `unix:/path/native` or anything else accepted in
+    /// `PULSE_SERVER`. Without this the server is picked from the environment and client.conf
+    pub fn server(mut self, server: &str) -> Self {
+        self.server = Some(server.to_string());
+        self
+    }
+
+    /// Add a property to the client's proplist, e.g. `application.icon_name`
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.properties.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Context flags such as `NOAUTOSPAWN` or `NOFAIL`
+    pub fn flags(mut self, flags: FlagSet) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Give up if the connection is not ready within `timeout`,
+    /// the same limit then applies to every request made through the handler
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Reconnect with growing delays whenever the connection to the server is lost, e.g. when
+    /// the daemon restarts. Requests in flight at that moment still fail, afterwards the
+    /// subscription is renewed and `Event::Reconnected` is queued. With a `timeout` the
+    /// attempts stop once it has passed and the waiting call fails with a timeout error
+    pub fn reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub fn connect(self) -> Result<Handler, PulseCtlError> {
         let mainloop = Rc::new(RefCell::new(
             Mainloop::new().expect("Failed to create mainloop"),
         ));
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let context = self.connect_context(&mainloop, deadline)?;
+        let introspect = context.introspect();
+        Ok(Handler {
+            mainloop,
+            context: Rc::new(RefCell::new(context)),
+            introspect,
+            events: Rc::new(RefCell::new(VecDeque::new())),
+            fades: HashMap::new(),
+            timeout: self.timeout,
+            subscription: None,
+            config: self,
+        })
+    }
 
-        let context = Rc::new(RefCell::new(
+    // open a new context on `mainloop` and wait until it is ready or `deadline` has passed
+    fn connect_context(
+        &self,
+        mainloop: &Rc<RefCell<Mainloop>>,
+        deadline: Option<Instant>,
+    ) -> Result<Context, PulseCtlError> {
+        let mut proplist = Proplist::new().unwrap();
+        proplist
+            .set_str(pulse::proplist::properties::APPLICATION_NAME, &self.name)
+            .unwrap();
+        for (key, value) in &self.properties {
+            proplist.set_str(key, value).map_err(|_| {
+                PulseCtlError::new(ConnectError, &format!("Invalid client property '{}'", key))
+            })?;
+        }
+
+        let mut context =
             Context::new_with_proplist(mainloop.borrow().deref(), "MainConn", &proplist)
-                .expect("Failed to create new context"),
-        ));
+                .expect("Failed to create new context");
 
-        context
-            .borrow_mut()
-            .connect(None, pulse::context::flags::NOFLAGS, None)
-            .expect("Failed to connect context");
+        context.connect(self.server.as_deref(), self.flags, None)?;
 
         loop {
-            match mainloop.borrow_mut().iterate(false) {
-                IterateResult::Err(e) => {
-                    eprintln!("iterate state was not success, quitting...");
-                    return Err(e.into());
-                }
-                IterateResult::Success(_) => {}
-                IterateResult::Quit(_) => {
-                    eprintln!("iterate state was not success, quitting...");
-                    return Err(PulseCtlError::new(
-                        ConnectError,
-                        "Iterate state quit without an error",
-                    ));
-                }
-            }
+            iterate(mainloop, deadline).inspect_err(|_e| {
+                warn!(error = ?_e, "mainloop failed while connecting");
+            })?;
 
-            match context.borrow().get_state() {
+            match context.get_state() {
                 pulse::context::State::Ready => break,
                 pulse::context::State::Failed | pulse::context::State::Terminated => {
-                    eprintln!("context state failed/terminated, quitting...");
+                    warn!(state = ?context.get_state(), "connection refused by the server");
                     return Err(PulseCtlError::new(
                         ConnectError,
                         "Context state failed/terminated without an error",
@@ -104,14 +217,29 @@ impl Handler {
                 }
                 _ => {}
             }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                warn!("timed out connecting to the server");
+                context.disconnect();
+                return Err(PulseCtlError::new(
+                    TimeoutError,
+                    "Timed out waiting for the server to accept the connection",
+                ));
+            }
         }
+        Ok(context)
+    }
+}
 
-        let introspect = context.borrow_mut().introspect();
-        Ok(Handler {
-            mainloop,
-            context,
-            introspect,
-        })
+impl Handler {
+    /// Connect to the default server, see `HandlerBuilder` for more options
+    pub fn connect(name: &str) -> Result<Handler, PulseCtlError> {
+        HandlerBuilder::new(name).connect()
+    }
+
+    /// How long requests may take before they fail with a timeout error, `None` waits forever
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
     }
 
     // loop until the passed operation is completed
@@ -119,32 +247,315 @@ impl Handler {
         &mut self,
         op: Operation<G>,
     ) -> Result<(), errors::PulseCtlError> {
+        self.wait_for_operations(vec![op]).pop().unwrap()
+    }
+
+    /// Wait for several operations at once, all of them sent before the first answer arrives
+    /// and sharing one deadline. Returns the outcome of each, in the order they were passed
+    pub fn wait_for_operations<G: ?Sized>(
+        &mut self,
+        ops: Vec<Operation<G>>,
+    ) -> Vec<Result<(), PulseCtlError>> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut pending: Vec<Option<Operation<G>>> = ops.into_iter().map(Some).collect();
+        let mut results: Vec<Result<(), PulseCtlError>> = pending.iter().map(|_| Ok(())).collect();
         loop {
-            match self.mainloop.borrow_mut().iterate(false) {
-                IterateResult::Err(e) => return Err(e.into()),
-                IterateResult::Success(_) => {}
-                IterateResult::Quit(_) => {
-                    return Err(PulseCtlError::new(
-                        OperationError,
-                        "Iterate state quit without an error",
-                    ));
+            for (slot, result) in pending.iter_mut().zip(results.iter_mut()) {
+                let state = match slot {
+                    Some(op) => op.get_state(),
+                    None => continue,
+                };
+                match state {
+                    State::Done => *slot = None,
+                    State::Running => {}
+                    State::Cancelled => {
+                        *result = Err(PulseCtlError::new(
+                            OperationError,
+                            "Operation cancelled without an error",
+                        ));
+                        *slot = None;
+                    }
                 }
             }
-            match op.get_state() {
-                State::Done => {
-                    break;
+            if pending.iter().all(Option::is_none) {
+                break;
+            }
+
+            let failure = if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                Some(PulseCtlError::new(
+                    TimeoutError,
+                    "Timed out waiting for the server to answer",
+                ))
+            } else {
+                match iterate(&self.mainloop, deadline)
+                    .and_then(|_| self.check_connection(deadline))
+                {
+                    Ok(false) => None,
+                    Ok(true) => Some(PulseCtlError::new(
+                        ConnectError,
+                        "Lost the connection to the server while waiting, the request was dropped",
+                    )),
+                    Err(error) => Some(error),
                 }
-                State::Running => {}
-                State::Cancelled => {
-                    return Err(PulseCtlError::new(
-                        OperationError,
-                        "Operation cancelled without an error",
-                    ));
+            };
+            if let Some(error) = failure {
+                // whatever is still running will never be answered, cancel it
+                for (slot, result) in pending.iter_mut().zip(results.iter_mut()) {
+                    if let Some(mut op) = slot.take() {
+                        op.cancel();
+                        *result = Err(error.clone());
+                    }
+                }
+                break;
+            }
+        }
+        results
+    }
+
+    /// Ask the server to notify us about changes to the facilities in `mask`,
+    /// received events are queued until fetched with `next_event` or `try_next_event`
+    pub fn subscribe(&mut self, mask: InterestMaskSet) -> Result<(), PulseCtlError> {
+        let _span = trace::operation("subscribe", None, None);
+        self.subscription = Some(mask);
+        let events_ref = self.events.clone();
+        self.context
+            .borrow_mut()
+            .set_subscribe_callback(Some(Box::new(move |facility, operation, index| {
+                if let (Some(facility), Some(operation)) = (facility, operation) {
+                    events_ref
+                        .borrow_mut()
+                        .push_back(Event::new(facility, operation, index));
                 }
+            })));
+
+        let success = Rc::new(RefCell::new(false));
+        let success_ref = success.clone();
+        let op = self
+            .context
+            .borrow_mut()
+            .subscribe(mask, move |res| success_ref.borrow_mut().clone_from(&res));
+        self.wait_for_operation(op)?;
+        let result = *success.borrow();
+        if result {
+            Ok(())
+        } else {
+            Err(PulseCtlError::new(
+                OperationError,
+                "Server refused the subscription request",
+            ))
+        }
+    }
+
+    // block until the server sends an event we subscribed to
+    pub fn next_event(&mut self) -> Result<Event, PulseCtlError> {
+        loop {
+            if let Some(event) = self.events.borrow_mut().pop_front() {
+                return Ok(event);
             }
+            iterate(&self.mainloop, None)?;
+            self.check_connection(None)?;
+        }
+    }
+
+    // return a queued event if there is one, without blocking. A lost connection gets one
+    // short reconnect attempt, and a timeout error when the server isn't back by then
+    pub fn try_next_event(&mut self) -> Result<Option<Event>, PulseCtlError> {
+        if self.events.borrow().is_empty() {
+            iterate(&self.mainloop, Some(Instant::now()))?;
+            self.check_connection(Some(Instant::now() + RECONNECT_DELAY))?;
+        }
+        Ok(self.events.borrow_mut().pop_front())
+    }
+
+    // run the mainloop until the fade has finished or was cancelled
+    pub fn wait_for_fade(&mut self, fade: &Fade) -> Result<(), PulseCtlError> {
+        while !fade.is_done() {
+            iterate(&self.mainloop, None)?;
+            self.check_connection(None)?;
         }
         Ok(())
     }
+
+    // notice a dead context after an iteration and, if enabled, replace it with a new one,
+    // trying until `deadline` or, without one, for as long as a request may take.
+    // Returns whether a reconnect happened
+    fn check_connection(&mut self, deadline: Option<Instant>) -> Result<bool, PulseCtlError> {
+        let state = self.context.borrow().get_state();
+        match state {
+            pulse::context::State::Failed | pulse::context::State::Terminated => {}
+            _ => return Ok(false),
+        }
+        if !self.config.reconnect {
+            return Err(PulseCtlError::new(
+                ConnectError,
+                "Lost the connection to the server",
+            ));
+        }
+
+        warn!("lost the connection to the server, reconnecting");
+        let deadline = deadline.or_else(|| self.timeout.map(|timeout| Instant::now() + timeout));
+        let mut delay = RECONNECT_DELAY;
+        let context = loop {
+            match self.config.connect_context(&self.mainloop, deadline) {
+                Ok(context) => break context,
+                Err(_e) => {
+                    debug!(error = ?_e, retry_in = ?delay, "reconnect attempt failed");
+                    let left =
+                        deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                    if left == Some(Duration::from_secs(0)) {
+                        warn!("gave up reconnecting to the server");
+                        return Err(PulseCtlError::new(
+                            TimeoutError,
+                            "Timed out reconnecting to the server",
+                        ));
+                    }
+                    thread::sleep(left.map_or(delay, |left| left.min(delay)));
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        };
+        // indexes are handed out anew by the restarted server, running fades point nowhere
+        for (fade, _) in self.fades.values() {
+            fade.cancel();
+        }
+        self.fades.clear();
+        self.introspect = context.introspect();
+        let old = std::mem::replace(&mut *self.context.borrow_mut(), context);
+        drop(old);
+
+        if let Some(mask) = self.subscription {
+            self.subscribe(mask)?;
+        }
+        self.events.borrow_mut().push_back(Event::Reconnected);
+        debug!("reconnected to the server");
+        Ok(true)
+    }
+}
+
+/// A `Handler` behind an `Rc`, for controllers that share one connection
+pub type SharedHandler = Rc<RefCell<Handler>>;
+
+/// Anything a controller can reach its `Handler` through: an owned handler, a mutable borrow
+/// of one or a `SharedHandler`
+pub trait HandlerRef {
+    type Guard<'a>: DerefMut<Target = Handler>
+    where
+        Self: 'a;
+
+    fn borrow_handler(&mut self) -> Self::Guard<'_>;
+}
+
+impl HandlerRef for Handler {
+    type Guard<'a> = &'a mut Handler;
+
+    fn borrow_handler(&mut self) -> &mut Handler {
+        self
+    }
+}
+
+impl<H: HandlerRef + ?Sized> HandlerRef for &mut H {
+    type Guard<'a>
+        = H::Guard<'a>
+    where
+        Self: 'a;
+
+    fn borrow_handler(&mut self) -> H::Guard<'_> {
+        (**self).borrow_handler()
+    }
+}
+
+/// The handler is borrowed for the length of each request, controllers sharing it must not be
+/// used from inside one another's callbacks
+impl HandlerRef for SharedHandler {
+    type Guard<'a> = RefMut<'a, Handler>;
+
+    fn borrow_handler(&mut self) -> RefMut<'_, Handler> {
+        self.borrow_mut()
+    }
+}
+
+/// One connection to the server, shared by every controller it hands out
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::{AppControl, DeviceControl};
+/// use pulsectl::PulseCtl;
+///
+/// fn main() {
+///     let pulse = PulseCtl::connect("mixer").expect("could not connect to PulseAudio");
+///     let mut sinks = pulse.sinks();
+///     let mut sources = pulse.sources();
+///
+///     let speakers = sinks.get_default_device().unwrap();
+///     let microphone = sources.get_default_device().unwrap();
+///     sources.set_device_mute_by_index(microphone.index, true).unwrap();
+///     for app in sinks.list_applications().unwrap() {
+///         sinks.move_app_by_index(app.index, speakers.index).unwrap();
+///     }
+///     for card in pulse.cards().list_cards().unwrap() {
+///         println!("{}", card);
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PulseCtl {
+    handler: SharedHandler,
+}
+
+impl PulseCtl {
+    /// Connect to the default server, use `from_handler` with a `HandlerBuilder` for more options
+    pub fn connect(name: &str) -> Result<Self, PulseCtlError> {
+        Ok(PulseCtl::from_handler(Handler::connect(name)?))
+    }
+
+    pub fn from_handler(handler: Handler) -> Self {
+        PulseCtl {
+            handler: Rc::new(RefCell::new(handler)),
+        }
+    }
+
+    /// The shared handler itself, e.g. to subscribe to events
+    pub fn handler(&self) -> SharedHandler {
+        self.handler.clone()
+    }
+
+    /// Playback devices and the applications playing to them
+    pub fn sinks(&self) -> DeviceController<Playback, SharedHandler> {
+        DeviceController::from_handler(self.handler())
+    }
+
+    /// Recording devices and the applications recording from them
+    pub fn sources(&self) -> DeviceController<Capture, SharedHandler> {
+        DeviceController::from_handler(self.handler())
+    }
+
+    pub fn cards(&self) -> CardController<SharedHandler> {
+        CardController::from_handler(self.handler())
+    }
+
+    pub fn modules(&self) -> ModuleController<SharedHandler> {
+        ModuleController::from_handler(self.handler())
+    }
+}
+
+// run a single iteration of the mainloop, sleeping until there is something to dispatch
+// or the deadline has passed
+fn iterate(mainloop: &RefCell<Mainloop>, deadline: Option<Instant>) -> Result<(), PulseCtlError> {
+    let mut mainloop = mainloop.borrow_mut();
+    // prepare takes milliseconds, round up so we don't wake up just short of the deadline
+    let timeout = deadline.map(|deadline| {
+        let left = deadline.saturating_duration_since(Instant::now());
+        left.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+    });
+    if mainloop.prepare(timeout).is_err() {
+        return Err(PulseCtlError::new(
+            OperationError,
+            "Iterate state quit without an error",
+        ));
+    }
+    mainloop.poll()?;
+    mainloop.dispatch()?;
+    Ok(())
 }
 
 impl Drop for Handler {
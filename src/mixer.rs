@@ -0,0 +1,295 @@
+/// A local copy of the server's devices, streams, cards and server info, kept current by
+/// subscription events
+///
+/// `MixerState` loads everything once and afterwards only refetches the objects the server says
+/// have changed, so reading it is cheap and never waits on the server. Call `update` regularly,
+/// e.g. once per frame, to apply whatever events arrived since. `generation` goes up with every
+/// change, and listeners added with `on_change` hear about each event after it was applied.
+///
+/// The state subscribes on the connection it is given and consumes its events, use a
+/// connection of its own when something else needs to watch events too.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::mixer::MixerState;
+/// use pulsectl::PulseCtl;
+///
+/// fn main() {
+///     let pulse = PulseCtl::connect("mixer-ui").unwrap();
+///     let mut mixer = MixerState::new(pulse).unwrap();
+///     let mut drawn = 0;
+///     loop {
+///         mixer.wait().unwrap();
+///         if mixer.generation() != drawn {
+///             for sink in mixer.sinks() {
+///                 println!("{}: {}", sink.index, sink.volume.print());
+///             }
+///             drawn = mixer.generation();
+///         }
+///     }
+/// }
+/// ```
+use std::collections::BTreeMap;
+
+use crate::controllers::errors::ControllerError;
+use crate::controllers::types::{ApplicationInfo, CardInfo, DeviceInfo, ServerInfo};
+use crate::controllers::{AppControl, DeviceControl};
+use crate::events::{subscription_masks, Event, Facility};
+use crate::PulseCtl;
+
+type Listener = Box<dyn FnMut(&Event)>;
+
+pub struct MixerState {
+    pulse: PulseCtl,
+    server: ServerInfo,
+    sinks: BTreeMap<u32, DeviceInfo>,
+    sources: BTreeMap<u32, DeviceInfo>,
+    sink_inputs: BTreeMap<u32, ApplicationInfo>,
+    source_outputs: BTreeMap<u32, ApplicationInfo>,
+    cards: BTreeMap<u32, CardInfo>,
+    generation: u64,
+    listeners: Vec<Listener>,
+}
+
+impl MixerState {
+    /// Subscribe to every facility the state tracks and load the current state of the server
+    pub fn new(pulse: PulseCtl) -> Result<Self, ControllerError> {
+        pulse.handler().borrow_mut().subscribe(
+            subscription_masks::SINK
+                | subscription_masks::SOURCE
+                | subscription_masks::SINK_INPUT
+                | subscription_masks::SOURCE_OUTPUT
+                | subscription_masks::MASK_CARD
+                | subscription_masks::SERVER,
+        )?;
+        let server = pulse.sinks().get_server_info()?;
+        let mut state = MixerState {
+            pulse,
+            server,
+            sinks: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            sink_inputs: BTreeMap::new(),
+            source_outputs: BTreeMap::new(),
+            cards: BTreeMap::new(),
+            generation: 0,
+            listeners: Vec::new(),
+        };
+        state.reload()?;
+        Ok(state)
+    }
+
+    /// The connection the state reads from, e.g. to make changes through its controllers
+    pub fn pulse(&self) -> &PulseCtl {
+        &self.pulse
+    }
+
+    /// Increases every time the state changes, compare with a remembered value to find out
+    /// whether anything needs to be redrawn
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Call `listener` with every event once the state has been updated for it,
+    /// `Event::Reconnected` follows a full reload
+    pub fn on_change<F: FnMut(&Event) + 'static>(&mut self, listener: F) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Apply every event that has arrived so far without waiting for more,
+    /// returns whether anything changed
+    pub fn update(&mut self) -> Result<bool, ControllerError> {
+        let generation = self.generation;
+        loop {
+            let event = self.pulse.handler().borrow_mut().try_next_event()?;
+            match event {
+                Some(event) => self.handle_event(event)?,
+                None => break,
+            }
+        }
+        Ok(self.generation != generation)
+    }
+
+    /// Block until the server reports a change, then apply it along with anything else queued
+    pub fn wait(&mut self) -> Result<(), ControllerError> {
+        let event = self.pulse.handler().borrow_mut().next_event()?;
+        self.handle_event(event)?;
+        self.update()?;
+        Ok(())
+    }
+
+    /// Apply a single subscription event, for callers that run their own event loop
+    pub fn handle_event(&mut self, event: Event) -> Result<(), ControllerError> {
+        match event {
+            Event::Reconnected => self.reload()?,
+            Event::Removed(facility, index) => {
+                self.remove(facility, index);
+            }
+            Event::New(facility, index) | Event::Changed(facility, index) => {
+                self.refresh(facility, index)?;
+            }
+        }
+        self.generation += 1;
+        for listener in self.listeners.iter_mut() {
+            listener(&event);
+        }
+        Ok(())
+    }
+
+    pub fn server_info(&self) -> &ServerInfo {
+        &self.server
+    }
+
+    pub fn sinks(&self) -> impl Iterator<Item = &DeviceInfo> {
+        self.sinks.values()
+    }
+
+    pub fn sink(&self, index: u32) -> Option<&DeviceInfo> {
+        self.sinks.get(&index)
+    }
+
+    pub fn sink_by_name(&self, name: &str) -> Option<&DeviceInfo> {
+        by_name(&self.sinks, name)
+    }
+
+    pub fn default_sink(&self) -> Option<&DeviceInfo> {
+        by_name(&self.sinks, self.server.default_sink_name.as_deref()?)
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &DeviceInfo> {
+        self.sources.values()
+    }
+
+    pub fn source(&self, index: u32) -> Option<&DeviceInfo> {
+        self.sources.get(&index)
+    }
+
+    pub fn source_by_name(&self, name: &str) -> Option<&DeviceInfo> {
+        by_name(&self.sources, name)
+    }
+
+    pub fn default_source(&self) -> Option<&DeviceInfo> {
+        by_name(&self.sources, self.server.default_source_name.as_deref()?)
+    }
+
+    /// Applications playing audio
+    pub fn sink_inputs(&self) -> impl Iterator<Item = &ApplicationInfo> {
+        self.sink_inputs.values()
+    }
+
+    pub fn sink_input(&self, index: u32) -> Option<&ApplicationInfo> {
+        self.sink_inputs.get(&index)
+    }
+
+    /// Applications recording audio
+    pub fn source_outputs(&self) -> impl Iterator<Item = &ApplicationInfo> {
+        self.source_outputs.values()
+    }
+
+    pub fn source_output(&self, index: u32) -> Option<&ApplicationInfo> {
+        self.source_outputs.get(&index)
+    }
+
+    pub fn cards(&self) -> impl Iterator<Item = &CardInfo> {
+        self.cards.values()
+    }
+
+    pub fn card(&self, index: u32) -> Option<&CardInfo> {
+        self.cards.get(&index)
+    }
+
+    // throw everything away and load it again
+    fn reload(&mut self) -> Result<(), ControllerError> {
+        let mut sinks = self.pulse.sinks();
+        let mut sources = self.pulse.sources();
+        self.server = sinks.get_server_info()?;
+        self.sinks = by_index(sinks.list_devices()?, |d| d.index);
+        self.sources = by_index(sources.list_devices()?, |d| d.index);
+        self.sink_inputs = by_index(sinks.list_applications()?, |a| a.index);
+        self.source_outputs = by_index(sources.list_applications()?, |a| a.index);
+        self.cards = by_index(self.pulse.cards().list_cards()?, |c| c.index);
+        self.generation += 1;
+        Ok(())
+    }
+
+    // fetch the new state of a single object, dropping it if it's already gone again
+    fn refresh(&mut self, facility: Facility, index: u32) -> Result<(), ControllerError> {
+        let found = match facility {
+            Facility::Sink => {
+                let sink = self.pulse.sinks().get_device_by_index(index);
+                insert(&mut self.sinks, index, sink)
+            }
+            Facility::Source => {
+                let source = self.pulse.sources().get_device_by_index(index);
+                insert(&mut self.sources, index, source)
+            }
+            Facility::SinkInput => {
+                let app = self.pulse.sinks().get_app_by_index(index);
+                insert(&mut self.sink_inputs, index, app)
+            }
+            Facility::SourceOutput => {
+                let app = self.pulse.sources().get_app_by_index(index);
+                insert(&mut self.source_outputs, index, app)
+            }
+            Facility::Card => {
+                let card = self.pulse.cards().get_card_by_index(index);
+                insert(&mut self.cards, index, card)
+            }
+            Facility::Server => {
+                self.server = self.pulse.sinks().get_server_info()?;
+                Ok(true)
+            }
+            _ => Ok(true),
+        }?;
+        if !found {
+            self.remove(facility, index);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, facility: Facility, index: u32) {
+        match facility {
+            Facility::Sink => {
+                self.sinks.remove(&index);
+            }
+            Facility::Source => {
+                self.sources.remove(&index);
+            }
+            Facility::SinkInput => {
+                self.sink_inputs.remove(&index);
+            }
+            Facility::SourceOutput => {
+                self.source_outputs.remove(&index);
+            }
+            Facility::Card => {
+                self.cards.remove(&index);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn by_index<T>(items: Vec<T>, index: impl Fn(&T) -> u32) -> BTreeMap<u32, T> {
+    items.into_iter().map(|item| (index(&item), item)).collect()
+}
+
+fn by_name<'a>(devices: &'a BTreeMap<u32, DeviceInfo>, name: &str) -> Option<&'a DeviceInfo> {
+    devices
+        .values()
+        .find(|device| device.name.as_deref() == Some(name))
+}
+
+// store a fetched object, false if the server no longer knew it
+fn insert<T>(
+    map: &mut BTreeMap<u32, T>,
+    index: u32,
+    fetched: Result<T, ControllerError>,
+) -> Result<bool, ControllerError> {
+    match fetched {
+        Ok(item) => {
+            map.insert(index, item);
+            Ok(true)
+        }
+        Err(e) if e.is_not_found() => Ok(false),
+        Err(e) => Err(e),
+    }
+}
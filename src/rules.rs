@@ -0,0 +1,575 @@
+/// Per-application routing and volume rules, enforced on new playback streams
+///
+/// Rules are read from a plain text file with one rule per line. Every `key=value` pair whose key
+/// is a stream property is a condition that must match, the remaining pairs are actions:
+///
+/// ```text
+/// # send Spotify to the USB DAC at 60%
+/// application.name=Spotify                device=alsa_output.usb-dac volume=60%
+/// # anything from firefox starts muted
+/// application.process.binary=firefox*     mute=yes
+/// # calls lower every other stream by half until they end
+/// media.role=phone                        duck=50%
+/// ```
+///
+/// Values may be quoted to include spaces and may contain `*` as a wildcard.
+/// Available actions are `device`, `device-id`, `volume`, `mute` and `duck`. `device-id` takes a
+/// `DeviceId` as written by its `Display` impl and keeps finding the device after it was
+/// re-plugged or renamed.
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use pulse::volume::{ChannelVolumes, Volume, VOLUME_NORM};
+
+use crate::controllers::{
+    errors::ControllerError,
+    identity::DeviceId,
+    types::{ApplicationInfo, DeviceInfo},
+    AppControl, DeviceControl, SinkController,
+};
+use crate::events::{subscription_masks, Event, Facility};
+
+/// Error returned when a rules file can't be read or parsed
+pub struct RuleParseError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Debug for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[RuleParseError]: line {}: {}", self.line, self.message)
+    }
+}
+
+/// A stream property that has to match for a rule to apply
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub key: String,
+    /// Expected value, `*` matches any run of characters.
+    pub pattern: String,
+}
+
+impl Condition {
+    pub fn matches(&self, app: &ApplicationInfo) -> bool {
+        app.proplist
+            .get_str(&self.key)
+            .is_some_and(|value| glob_match(&self.pattern, &value))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Move the stream to the device with this name.
+    Device(String),
+    /// Move the stream to the device with this identity.
+    DeviceId(DeviceId),
+    /// Set the stream volume, 1.0 being 100%.
+    Volume(f64),
+    /// Mute or unmute the stream.
+    Mute(bool),
+    /// While the stream exists lower every other stream by this fraction.
+    Duck(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+impl Rule {
+    /// A rule matches when all of its conditions do
+    pub fn matches(&self, app: &ApplicationInfo) -> bool {
+        self.conditions.iter().all(|c| c.matches(app))
+    }
+}
+
+/// Parse a whole rules file, blank lines and lines starting with `#` are skipped
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, RuleParseError> {
+    let mut rules = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| RuleParseError {
+            line: number + 1,
+            message,
+        };
+        let mut rule = Rule {
+            conditions: Vec::new(),
+            actions: Vec::new(),
+        };
+        for token in tokenize(line).map_err(|m| error(m.to_string()))? {
+            let (key, value) = match token.find('=') {
+                Some(pos) => (&token[..pos], token[pos + 1..].to_string()),
+                None => return Err(error(format!("expected key=value, found '{}'", token))),
+            };
+            let action = match key {
+                "device" => Action::Device(value),
+                "device-id" => Action::DeviceId(
+                    value
+                        .parse()
+                        .map_err(|e| error(format!("invalid device-id: {:?}", e)))?,
+                ),
+                "volume" => Action::Volume(parse_percent(&value).map_err(error)?),
+                "mute" => Action::Mute(parse_bool(&value).map_err(error)?),
+                "duck" => Action::Duck(parse_percent(&value).map_err(error)?.min(1.0)),
+                _ => {
+                    rule.conditions.push(Condition {
+                        key: key.to_string(),
+                        pattern: value,
+                    });
+                    continue;
+                }
+            };
+            rule.actions.push(action);
+        }
+        if rule.conditions.is_empty() {
+            return Err(error("rule has no conditions".to_string()));
+        }
+        if rule.actions.is_empty() {
+            return Err(error("rule has no actions".to_string()));
+        }
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Watches for new playback streams and applies every rule that matches them. Any controller
+/// can drive it by hand through `handle_event`, `run` needs a `SinkController`
+pub struct RulesEngine<C = SinkController> {
+    pub controller: C,
+    rules: Vec<Rule>,
+    // streams currently ducking the others, with the fraction they duck by
+    duckers: HashMap<u32, f64>,
+    // volumes of the streams we lowered, to put back once all duckers are gone
+    ducked: HashMap<u32, ChannelVolumes>,
+}
+
+impl<C> RulesEngine<C> {
+    pub fn new(controller: C, rules: Vec<Rule>) -> Self {
+        RulesEngine {
+            controller,
+            rules,
+            duckers: HashMap::new(),
+            ducked: HashMap::new(),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(controller: C, path: P) -> Result<Self, RuleParseError> {
+        let text = fs::read_to_string(path).map_err(|e| RuleParseError {
+            line: 0,
+            message: e.to_string(),
+        })?;
+        Ok(RulesEngine::new(controller, parse_rules(&text)?))
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+impl RulesEngine<SinkController> {
+    /// Apply the rules to streams that were already playing, then enforce them on new streams
+    /// until an error occurs
+    pub fn run(&mut self) -> Result<(), ControllerError> {
+        self.controller
+            .handler
+            .subscribe(subscription_masks::SINK_INPUT)?;
+        for app in self.controller.list_applications()? {
+            self.stream_added(&app)?;
+        }
+        loop {
+            let event = self.controller.handler.next_event()?;
+            self.handle_event(event)?;
+        }
+    }
+}
+
+impl<C> RulesEngine<C>
+where
+    C: DeviceControl<DeviceInfo> + AppControl<ApplicationInfo>,
+{
+    /// React to a single subscription event, for callers that run their own event loop
+    pub fn handle_event(&mut self, event: Event) -> Result<(), ControllerError> {
+        if event == Event::Reconnected {
+            // the server restarted, apply the rules again to whatever is playing now
+            self.duckers.clear();
+            self.ducked.clear();
+            for app in self.controller.list_applications()? {
+                self.stream_added(&app)?;
+            }
+            return Ok(());
+        }
+        if event.facility() != Some(Facility::SinkInput) {
+            return Ok(());
+        }
+        match event {
+            Event::New(_, index) => {
+                // the stream may already be gone again by the time we ask for it
+                if let Ok(app) = self.controller.get_app_by_index(index) {
+                    self.stream_added(&app)?;
+                }
+            }
+            Event::Removed(_, index) => self.stream_removed(index)?,
+            Event::Changed(..) | Event::Reconnected => {}
+        }
+        Ok(())
+    }
+
+    fn stream_added(&mut self, app: &ApplicationInfo) -> Result<(), ControllerError> {
+        let actions: Vec<Action> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(app))
+            .flat_map(|rule| rule.actions.iter().cloned())
+            .collect();
+        for action in actions {
+            match action {
+                Action::Device(name) => {
+                    self.controller.move_app_by_name(app.index, &name)?;
+                }
+                Action::DeviceId(id) => {
+                    // like with names, a device that isn't there leaves the stream alone
+                    match self.controller.get_device_by_id(&id) {
+                        Ok(device) => {
+                            self.controller.move_app_by_index(app.index, device.index)?;
+                        }
+                        Err(e) if e.is_not_found() => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                Action::Volume(volume) => {
+                    let mut volumes = app.volume;
+                    volumes.set(
+                        u32::from(volumes.len()),
+                        Volume((volume * f64::from(VOLUME_NORM.0)) as u32),
+                    );
+                    self.controller.set_app_volume(app.index, &volumes)?;
+                }
+                Action::Mute(mute) => {
+                    self.controller.set_app_mute(app.index, mute)?;
+                }
+                Action::Duck(amount) => {
+                    self.duckers.insert(app.index, amount);
+                }
+            }
+        }
+        self.update_ducking()
+    }
+
+    fn stream_removed(&mut self, index: u32) -> Result<(), ControllerError> {
+        self.ducked.remove(&index);
+        if self.duckers.remove(&index).is_some() {
+            self.update_ducking()?;
+        }
+        Ok(())
+    }
+
+    fn update_ducking(&mut self) -> Result<(), ControllerError> {
+        if self.duckers.is_empty() {
+            let ducked: Vec<(u32, ChannelVolumes)> = self.ducked.drain().collect();
+            for (index, volume) in ducked {
+                self.controller.set_app_volume(index, &volume)?;
+            }
+            return Ok(());
+        }
+        // the strongest ducker wins, and every lowered stream is set from the volume it had
+        // before ducking, so the level follows duckers as they come and go
+        let amount = self.duckers.values().cloned().fold(0.0, f64::max);
+        for app in self.controller.list_applications()? {
+            if self.duckers.contains_key(&app.index) {
+                continue;
+            }
+            let mut volume = *self.ducked.entry(app.index).or_insert(app.volume);
+            for channel in volume.get_mut() {
+                *channel = Volume((f64::from(channel.0) * (1.0 - amount)) as u32);
+            }
+            self.controller.set_app_volume(app.index, &volume)?;
+        }
+        Ok(())
+    }
+}
+
+// split a line on whitespace, keeping double quoted runs together
+fn tokenize(line: &str) -> Result<Vec<String>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote");
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    value
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .ok()
+        .filter(|v| *v >= 0.0 && v.is_finite())
+        .map(|v| v / 100.0)
+        .ok_or_else(|| format!("invalid percentage '{}'", value))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "yes" | "true" | "on" | "1" => Ok(true),
+        "no" | "false" | "off" | "0" => Ok(false),
+        _ => Err(format!("invalid boolean '{}'", value)),
+    }
+}
+
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    let mut rest = match value
+        .strip_prefix(first)
+        .and_then(|rest| rest.strip_suffix(last))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_around_wildcards() {
+        assert!(glob_match("firefox", "firefox"));
+        assert!(!glob_match("firefox", "firefox-bin"));
+        assert!(glob_match("firefox*", "firefox-bin"));
+        assert!(glob_match("*bin", "firefox-bin"));
+        assert!(glob_match("f*x*n", "firefox-bin"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("a*a", "a"));
+        assert!(!glob_match("f*z*n", "firefox-bin"));
+    }
+
+    #[test]
+    fn globs_handle_multibyte_values() {
+        assert!(!glob_match("a*b", "aé"));
+        assert!(!glob_match("é*b", "aé"));
+        assert!(glob_match("a*é", "aé"));
+        assert!(glob_match("*ü*", "Müsik"));
+    }
+
+    #[test]
+    fn tokens_split_on_whitespace_outside_quotes() {
+        assert_eq!(
+            tokenize("a=1   b=\"two words\"\tc").unwrap(),
+            vec!["a=1", "b=two words", "c"]
+        );
+        assert!(tokenize("").unwrap().is_empty());
+        assert!(tokenize("a=\"open").is_err());
+    }
+
+    #[test]
+    fn rules_split_into_conditions_and_actions() {
+        let text = "# comment\n\n\
+                    application.name=\"Music Player\" device=speakers volume=60% mute=no\n\
+                    media.role=phone duck=150%\n";
+        let rules = parse_rules(text).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules[0],
+            Rule {
+                conditions: vec![Condition {
+                    key: "application.name".to_string(),
+                    pattern: "Music Player".to_string(),
+                }],
+                actions: vec![
+                    Action::Device("speakers".to_string()),
+                    Action::Volume(0.6),
+                    Action::Mute(false),
+                ],
+            }
+        );
+        assert_eq!(rules[1].actions, vec![Action::Duck(1.0)]);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = parse_rules("a=1 mute=yes\nmute=yes\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(parse_rules("a=1 volume=loud").unwrap_err().line, 1);
+        assert_eq!(parse_rules("a=1 mute=maybe").unwrap_err().line, 1);
+        assert_eq!(parse_rules("a=1 nokey").unwrap_err().line, 1);
+        assert_eq!(parse_rules("a=\"1").unwrap_err().line, 1);
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod engine_tests {
+    use super::*;
+    use crate::controllers::mock::{Call, MockController};
+    use crate::controllers::types::{ApplicationInfoBuilder, DeviceInfoBuilder};
+
+    fn stereo(volume: u32) -> ChannelVolumes {
+        let mut volumes = ChannelVolumes::default();
+        volumes.set(2, Volume(volume));
+        volumes
+    }
+
+    fn mock() -> MockController {
+        MockController::new()
+            .with_device(DeviceInfoBuilder::new(0, "speakers").build())
+            .with_device(
+                DeviceInfoBuilder::new(1, "dac")
+                    .property("device.serial", "DAC42")
+                    .build(),
+            )
+            .with_app(
+                ApplicationInfoBuilder::new(7, "Music")
+                    .application("Spotify")
+                    .volume(stereo(60000))
+                    .build(),
+            )
+    }
+
+    fn engine_for(rules: &str) -> RulesEngine<MockController> {
+        RulesEngine::new(mock(), parse_rules(rules).unwrap())
+    }
+
+    fn app(engine: &RulesEngine<MockController>, index: u32) -> &ApplicationInfo {
+        let apps = &engine.controller.applications;
+        apps.iter().find(|a| a.index == index).unwrap()
+    }
+
+    // add a stream to the mock and tell the engine about it
+    fn start(engine: &mut RulesEngine<MockController>, app: ApplicationInfo) {
+        let index = app.index;
+        engine.controller.applications.push(app);
+        engine
+            .handle_event(Event::New(Facility::SinkInput, index))
+            .unwrap();
+    }
+
+    fn stop(engine: &mut RulesEngine<MockController>, index: u32) {
+        engine.controller.applications.retain(|a| a.index != index);
+        engine
+            .handle_event(Event::Removed(Facility::SinkInput, index))
+            .unwrap();
+    }
+
+    #[test]
+    fn matching_streams_are_routed_and_adjusted() {
+        let mut engine = engine_for("application.name=Spot* device=dac volume=50% mute=yes");
+        engine
+            .handle_event(Event::New(Facility::SinkInput, 7))
+            .unwrap();
+        let music = app(&engine, 7);
+        assert_eq!(music.connection_id, 1);
+        assert_eq!(music.volume, stereo(VOLUME_NORM.0 / 2));
+        assert!(music.mute);
+    }
+
+    #[test]
+    fn other_streams_are_left_alone() {
+        let mut engine = engine_for("application.name=Firefox device=dac mute=yes");
+        engine
+            .handle_event(Event::New(Facility::SinkInput, 7))
+            .unwrap();
+        let music = app(&engine, 7);
+        assert_eq!(music.connection_id, 0);
+        assert!(!music.mute);
+        assert!(engine
+            .controller
+            .calls()
+            .iter()
+            .all(|call| !matches!(call, Call::MoveAppByName(..) | Call::SetAppMute(..))));
+    }
+
+    #[test]
+    fn device_ids_route_to_the_matching_device() {
+        let mut engine = engine_for("application.name=Spotify device-id=serial=DAC42;name=dac");
+        engine
+            .handle_event(Event::New(Facility::SinkInput, 7))
+            .unwrap();
+        assert_eq!(app(&engine, 7).connection_id, 1);
+
+        // a device that isn't there leaves the stream where it is
+        let mut engine = engine_for("application.name=Spotify device-id=serial=missing;name=dac");
+        engine
+            .handle_event(Event::New(Facility::SinkInput, 7))
+            .unwrap();
+        assert_eq!(app(&engine, 7).connection_id, 0);
+    }
+
+    #[test]
+    fn streams_that_vanished_are_ignored() {
+        let mut engine = engine_for("application.name=Spotify mute=yes");
+        engine
+            .handle_event(Event::New(Facility::SinkInput, 99))
+            .unwrap();
+        assert!(!app(&engine, 7).mute);
+    }
+
+    #[test]
+    fn calls_duck_other_streams_until_they_end() {
+        let mut engine = engine_for("media.role=phone duck=50%");
+        let call = ApplicationInfoBuilder::new(8, "Call")
+            .property("media.role", "phone")
+            .build();
+        start(&mut engine, call);
+        assert_eq!(app(&engine, 7).volume, stereo(30000));
+        assert_eq!(app(&engine, 8).volume, stereo(VOLUME_NORM.0));
+
+        stop(&mut engine, 8);
+        assert_eq!(app(&engine, 7).volume, stereo(60000));
+    }
+
+    #[test]
+    fn the_strongest_ducker_sets_the_level() {
+        let mut engine = engine_for("media.role=phone duck=50%\nmedia.role=alarm duck=75%");
+        let call = ApplicationInfoBuilder::new(8, "Call")
+            .property("media.role", "phone")
+            .build();
+        let alarm = ApplicationInfoBuilder::new(9, "Alarm")
+            .property("media.role", "alarm")
+            .build();
+        start(&mut engine, call);
+        assert_eq!(app(&engine, 7).volume, stereo(30000));
+
+        // the alarm lowers the music further and the call isn't ducked by either
+        start(&mut engine, alarm);
+        assert_eq!(app(&engine, 7).volume, stereo(15000));
+        assert_eq!(app(&engine, 8).volume, stereo(VOLUME_NORM.0));
+
+        // back to the call's level once the alarm ends, not left at the alarm's
+        stop(&mut engine, 9);
+        assert_eq!(app(&engine, 7).volume, stereo(30000));
+
+        stop(&mut engine, 8);
+        assert_eq!(app(&engine, 7).volume, stereo(60000));
+    }
+}
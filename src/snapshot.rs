@@ -0,0 +1,265 @@
+/// Save the state of the whole mixer and put it back later
+///
+/// Indexes are handed out again every time a device or stream reconnects, so a snapshot
+/// refers to devices by name and to streams by their application name instead.
+/// ```no_run
+/// extern crate pulsectl;
+///
+/// use pulsectl::controllers::{SinkController, SourceController};
+/// use pulsectl::snapshot::MixerSnapshot;
+///
+/// fn main() {
+///     let mut sinks = SinkController::create();
+///     let mut sources = SourceController::create();
+///     let snapshot = MixerSnapshot::capture(&mut sinks, &mut sources).unwrap();
+///     // ... change things around ...
+///     let report = snapshot.restore(&mut sinks, &mut sources).unwrap();
+///     for name in report.unmatched_devices {
+///         println!("{} is gone", name);
+///     }
+/// }
+/// ```
+use pulse::{proplist::properties, volume::ChannelVolumes};
+
+use crate::controllers::{
+    errors::ControllerError,
+    types::{ApplicationInfo, DeviceInfo},
+    AppControl, DeviceControl,
+};
+
+/// Saved state of a single sink or source
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceState {
+    /// Name of the device, used to find it again on restore.
+    pub name: String,
+    /// Volume of every channel.
+    pub volume: ChannelVolumes,
+    /// Mute switch.
+    pub mute: bool,
+    /// Name of the active port, if the device has ports.
+    pub active_port: Option<String>,
+    /// Whether this was the default device.
+    pub is_default: bool,
+}
+
+/// Saved state of a single application stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppState {
+    /// The `application.name` property of the stream, or its name if that is not set.
+    pub key: String,
+    /// Volume of every channel.
+    pub volume: ChannelVolumes,
+    /// Mute switch.
+    pub mute: bool,
+    /// Name of the device the stream was connected to.
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixerSnapshot {
+    pub sinks: Vec<DeviceState>,
+    pub sources: Vec<DeviceState>,
+    /// Playback streams (sink inputs).
+    pub playback: Vec<AppState>,
+    /// Recording streams (source outputs).
+    pub recording: Vec<AppState>,
+}
+
+/// Everything from a snapshot that could not be put back onto the server
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RestoreReport {
+    /// Names of saved devices that are no longer present.
+    pub unmatched_devices: Vec<String>,
+    /// Keys of saved streams that are no longer playing or recording.
+    pub unmatched_apps: Vec<String>,
+    /// Human readable descriptions of changes the server refused.
+    pub failed: Vec<String>,
+}
+
+impl RestoreReport {
+    pub fn is_complete(&self) -> bool {
+        self.unmatched_devices.is_empty()
+            && self.unmatched_apps.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+impl MixerSnapshot {
+    /// Record the current state of every sink, source and stream
+    pub fn capture<S, C>(sinks: &mut S, sources: &mut C) -> Result<Self, ControllerError>
+    where
+        S: DeviceControl<DeviceInfo> + AppControl<ApplicationInfo>,
+        C: DeviceControl<DeviceInfo> + AppControl<ApplicationInfo>,
+    {
+        Ok(MixerSnapshot {
+            sinks: capture_devices(sinks)?,
+            sources: capture_devices(sources)?,
+            playback: capture_apps(sinks)?,
+            recording: capture_apps(sources)?,
+        })
+    }
+
+    /// Apply the saved state to whatever is currently on the server,
+    /// anything that could not be matched by name is listed in the returned report
+    pub fn restore<S, C>(
+        &self,
+        sinks: &mut S,
+        sources: &mut C,
+    ) -> Result<RestoreReport, ControllerError>
+    where
+        S: DeviceControl<DeviceInfo> + AppControl<ApplicationInfo>,
+        C: DeviceControl<DeviceInfo> + AppControl<ApplicationInfo>,
+    {
+        let mut report = RestoreReport::default();
+        restore_devices(sinks, &self.sinks, &mut report)?;
+        restore_devices(sources, &self.sources, &mut report)?;
+        restore_apps(sinks, &self.playback, &mut report)?;
+        restore_apps(sources, &self.recording, &mut report)?;
+        Ok(report)
+    }
+}
+
+fn app_key(app: &ApplicationInfo) -> Option<String> {
+    app.proplist
+        .get_str(properties::APPLICATION_NAME)
+        .or_else(|| app.name.clone())
+}
+
+fn capture_devices<C>(controller: &mut C) -> Result<Vec<DeviceState>, ControllerError>
+where
+    C: DeviceControl<DeviceInfo>,
+{
+    let default_name = controller.get_default_device().ok().and_then(|d| d.name);
+    Ok(controller
+        .list_devices()?
+        .into_iter()
+        .filter_map(|device| {
+            let name = device.name?;
+            Some(DeviceState {
+                is_default: default_name.as_ref() == Some(&name),
+                name,
+                volume: device.volume,
+                mute: device.mute,
+                active_port: device.active_port.and_then(|port| port.name),
+            })
+        })
+        .collect())
+}
+
+fn capture_apps<C>(controller: &mut C) -> Result<Vec<AppState>, ControllerError>
+where
+    C: DeviceControl<DeviceInfo> + AppControl<ApplicationInfo>,
+{
+    let devices = controller.list_devices()?;
+    Ok(controller
+        .list_applications()?
+        .into_iter()
+        .filter_map(|app| {
+            let device = devices
+                .iter()
+                .find(|d| d.index == app.connection_id)
+                .and_then(|d| d.name.clone());
+            Some(AppState {
+                key: app_key(&app)?,
+                volume: app.volume,
+                mute: app.mute,
+                device,
+            })
+        })
+        .collect())
+}
+
+fn restore_devices<C>(
+    controller: &mut C,
+    saved: &[DeviceState],
+    report: &mut RestoreReport,
+) -> Result<(), ControllerError>
+where
+    C: DeviceControl<DeviceInfo>,
+{
+    let live = controller.list_devices()?;
+    for state in saved {
+        let device = match live.iter().find(|d| d.name.as_ref() == Some(&state.name)) {
+            Some(device) => device,
+            None => {
+                report.unmatched_devices.push(state.name.clone());
+                continue;
+            }
+        };
+        if let Some(port) = &state.active_port {
+            let current = device.active_port.as_ref().and_then(|p| p.name.as_ref());
+            if current != Some(port) && !controller.set_device_port_by_name(&state.name, port)? {
+                report
+                    .failed
+                    .push(format!("set port {} on {}", port, state.name));
+            }
+        }
+        let volume = fit_volume(&state.volume, &device.volume);
+        controller.set_device_volume_by_name(&state.name, &volume);
+        if !controller.set_device_mute_by_name(&state.name, state.mute)? {
+            report.failed.push(format!("set mute on {}", state.name));
+        }
+        if state.is_default && !controller.set_default_device(&state.name)? {
+            report
+                .failed
+                .push(format!("make {} the default", state.name));
+        }
+    }
+    Ok(())
+}
+
+fn restore_apps<C>(
+    controller: &mut C,
+    saved: &[AppState],
+    report: &mut RestoreReport,
+) -> Result<(), ControllerError>
+where
+    C: AppControl<ApplicationInfo>,
+{
+    // each live stream takes the state of one saved stream, the n-th saved stream of a key
+    // goes to the n-th live stream with that key
+    let mut live: Vec<Option<ApplicationInfo>> = controller
+        .list_applications()?
+        .into_iter()
+        .map(Some)
+        .collect();
+    for state in saved {
+        let app = match live
+            .iter_mut()
+            .find(|app| app.as_ref().and_then(app_key).as_ref() == Some(&state.key))
+            .and_then(Option::take)
+        {
+            Some(app) => app,
+            None => {
+                report.unmatched_apps.push(state.key.clone());
+                continue;
+            }
+        };
+        if let Some(device) = &state.device {
+            if !controller.move_app_by_name(app.index, device)? {
+                report
+                    .failed
+                    .push(format!("move {} to {}", state.key, device));
+            }
+        }
+        let volume = fit_volume(&state.volume, &app.volume);
+        if !controller.set_app_volume(app.index, &volume)? {
+            report.failed.push(format!("set volume on {}", state.key));
+        }
+        if !controller.set_app_mute(app.index, state.mute)? {
+            report.failed.push(format!("set mute on {}", state.key));
+        }
+    }
+    Ok(())
+}
+
+// a device may come back with a different channel count (e.g. after a profile change),
+// in that case the average of the saved volume is applied to every channel
+fn fit_volume(saved: &ChannelVolumes, live: &ChannelVolumes) -> ChannelVolumes {
+    if saved.len() == live.len() {
+        return *saved;
+    }
+    let mut volume = *live;
+    volume.set(u32::from(live.len()), saved.avg());
+    volume
+}
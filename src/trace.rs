@@ -0,0 +1,62 @@
+/// Diagnostics through the `tracing` facade, compiled to nothing unless the `tracing` feature
+/// is enabled. The library never prints on its own, install a subscriber to see these
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    };
+}
+
+/// Span covering a single request to the server, entered until dropped.
+/// The time the request took is logged when it ends
+#[cfg(feature = "tracing")]
+pub(crate) struct OperationSpan {
+    _span: tracing::span::EnteredSpan,
+    started: Instant,
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct OperationSpan;
+
+#[cfg(feature = "tracing")]
+impl Drop for OperationSpan {
+    fn drop(&mut self) {
+        tracing::debug!(
+            elapsed_us = self.started.elapsed().as_micros() as u64,
+            "operation finished"
+        );
+    }
+}
+
+/// `operation` is the name of the PulseAudio call, `index` and `name` identify its target
+#[cfg(feature = "tracing")]
+pub(crate) fn operation(
+    operation: &'static str,
+    index: Option<u32>,
+    name: Option<&str>,
+) -> OperationSpan {
+    let span = tracing::debug_span!("pulse_operation", operation, index, name);
+    OperationSpan {
+        _span: span.entered(),
+        started: Instant::now(),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn operation(
+    _operation: &'static str,
+    _index: Option<u32>,
+    _name: Option<&str>,
+) -> OperationSpan {
+    OperationSpan
+}
@@ -0,0 +1,63 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::{AppControl, DeviceControl};
+
+use common::{find_app, percent, volume, SINK_A, SINK_B};
+
+#[test]
+fn results_follow_queue_order() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let sine = find_app(&mut sinks, "module-sine.c");
+    let sink_b = sinks.get_device_by_name(SINK_B).unwrap().index;
+
+    let results = sinks
+        .batch()
+        .set_app_volume(sine.index, &volume(20.0))
+        .set_app_mute(9999, true)
+        .move_app_by_index(sine.index, sink_b)
+        .set_device_mute_by_name(SINK_A, true)
+        .run();
+    let answers: Vec<bool> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(answers, vec![true, false, true, true]);
+
+    let app = sinks.get_app_by_index(sine.index).unwrap();
+    assert_eq!(percent(&app.volume), 20);
+    assert_eq!(app.connection_id, sink_b);
+    assert!(sinks.get_device_by_name(SINK_A).unwrap().mute);
+}
+
+#[test]
+fn empty_batch() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let batch = sinks.batch();
+    assert!(batch.is_empty());
+    assert!(batch.run().is_empty());
+}
+
+#[test]
+fn batch_over_a_shared_connection() {
+    let server = test_server!();
+    let pulse = server.pulse();
+    let mut sinks = pulse.sinks();
+    let results = sinks
+        .batch()
+        .set_device_volume_by_name(SINK_A, &volume(40.0))
+        .set_device_volume_by_name(SINK_B, &volume(60.0))
+        .set_default_device(SINK_B)
+        .run();
+    assert!(results.into_iter().all(|r| r.unwrap()));
+    assert_eq!(
+        percent(&sinks.get_device_by_name(SINK_A).unwrap().volume),
+        40
+    );
+    assert_eq!(
+        sinks.get_default_device().unwrap().name.as_deref(),
+        Some(SINK_B)
+    );
+}
@@ -0,0 +1,183 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulse::proplist::Proplist;
+use pulsectl::controllers::bluetooth::ProfileKind;
+use pulsectl::controllers::types::{CardInfo, CardProfileInfo};
+use pulsectl::controllers::{CardController, DeviceControl};
+
+use common::SINK_A;
+
+#[test]
+fn profile_kinds_follow_the_profile_names() {
+    for name in &["a2dp_sink", "a2dp-sink-aac", "a2dp_sink_ldac"] {
+        assert_eq!(ProfileKind::from_profile_name(name), ProfileKind::HiFi);
+    }
+    for name in &[
+        "headset_head_unit",
+        "handsfree_head_unit",
+        "headset-head-unit-msbc",
+    ] {
+        assert_eq!(ProfileKind::from_profile_name(name), ProfileKind::Headset);
+    }
+    assert_eq!(ProfileKind::from_profile_name("off"), ProfileKind::Off);
+    assert_eq!(
+        ProfileKind::from_profile_name("output:analog-stereo"),
+        ProfileKind::Other
+    );
+}
+
+fn profile(name: &str, n_sinks: u32, priority: u32, available: bool) -> CardProfileInfo {
+    CardProfileInfo {
+        name: Some(name.to_string()),
+        description: None,
+        n_sinks,
+        n_sources: 1,
+        priority,
+        available,
+    }
+}
+
+// a card with the given properties and profiles, the first profile active
+fn card(properties: &[(&str, &str)], profiles: Vec<CardProfileInfo>) -> CardInfo {
+    let mut proplist = Proplist::new().unwrap();
+    for (key, value) in properties {
+        proplist.set_str(key, value).unwrap();
+    }
+    CardInfo {
+        index: 3,
+        name: Some("bluez_card.00_1B_66_AA_BB_CC".to_string()),
+        owner_module: None,
+        driver: None,
+        proplist,
+        ports: Vec::new(),
+        active_profile: profiles.first().cloned(),
+        profiles,
+    }
+}
+
+fn headset(properties: &[(&str, &str)]) -> CardInfo {
+    let mut all = vec![
+        ("device.bus", "bluetooth"),
+        ("device.string", "00:1B:66:AA:BB:CC"),
+    ];
+    all.extend_from_slice(properties);
+    card(
+        &all,
+        vec![
+            profile("a2dp_sink", 1, 40, true),
+            profile("a2dp_sink_ldac", 1, 50, false),
+            profile("headset_head_unit", 1, 30, true),
+            profile("handsfree_head_unit", 0, 60, true),
+            profile("off", 0, 0, true),
+        ],
+    )
+}
+
+#[test]
+fn cards_carry_address_and_codec() {
+    let info = headset(&[("bluetooth.codec", "aac")]).bluetooth().unwrap();
+    assert_eq!(info.card, 3);
+    assert_eq!(info.address.as_deref(), Some("00:1B:66:AA:BB:CC"));
+    assert_eq!(info.codec.as_deref(), Some("aac"));
+    assert_eq!(info.mode(), Some(ProfileKind::HiFi));
+
+    // PipeWire's keys
+    let pipewire = card(
+        &[
+            ("api.bluez5.address", "00:1B:66:AA:BB:CC"),
+            ("api.bluez5.codec", "ldac"),
+        ],
+        Vec::new(),
+    );
+    let info = pipewire.bluetooth().unwrap();
+    assert_eq!(info.address.as_deref(), Some("00:1B:66:AA:BB:CC"));
+    assert_eq!(info.codec.as_deref(), Some("ldac"));
+
+    assert_eq!(headset(&[]).bluetooth().unwrap().codec, None);
+    assert!(card(&[("device.bus", "pci")], Vec::new())
+        .bluetooth()
+        .is_none());
+}
+
+#[test]
+fn best_profiles_skip_unavailable_ones_and_prefer_sinks() {
+    let info = headset(&[]).bluetooth().unwrap();
+    let name = |kind| {
+        info.best_profile(kind)
+            .and_then(|profile| profile.info.name.clone())
+    };
+    // the higher priority LDAC profile is unavailable, and no headset profile is A2DP
+    assert_eq!(name(ProfileKind::HiFi).as_deref(), Some("a2dp_sink"));
+    // a profile with a sink beats a higher priority one without
+    assert_eq!(
+        name(ProfileKind::Headset).as_deref(),
+        Some("headset_head_unit")
+    );
+    assert_eq!(name(ProfileKind::Off).as_deref(), Some("off"));
+
+    let bare = card(&[("device.bus", "bluetooth")], Vec::new());
+    let info = bare.bluetooth().unwrap();
+    assert!(info.best_profile(ProfileKind::HiFi).is_none());
+    assert!(info.best_profile(ProfileKind::Headset).is_none());
+    assert_eq!(info.mode(), None);
+}
+
+#[test]
+fn battery_levels_are_percentages() {
+    let battery = |level: &str| {
+        headset(&[("bluetooth.battery", level)])
+            .bluetooth()
+            .unwrap()
+            .battery
+    };
+    assert_eq!(battery("80"), Some(80));
+    assert_eq!(battery("80%"), Some(80));
+    assert_eq!(battery("100"), Some(100));
+    assert_eq!(battery("0"), Some(0));
+    assert_eq!(battery("150"), None);
+    assert_eq!(battery("300"), None);
+    assert_eq!(battery("-5"), None);
+    assert_eq!(battery("full"), None);
+    assert_eq!(battery(""), None);
+    assert_eq!(headset(&[]).bluetooth().unwrap().battery, None);
+}
+
+#[test]
+fn bluetooth_properties_are_read_from_devices() {
+    let server = test_server!();
+    server
+        .pulse()
+        .modules()
+        .load_module(
+            "module-null-sink",
+            "sink_name=pulsectl_test_headset sink_properties='device.bus=\"bluetooth\" \
+             device.string=\"00:1B:66:AA:BB:CC\" bluetooth.codec=\"aac\"'",
+        )
+        .unwrap();
+    let mut sinks = server.sinks();
+
+    let headset = sinks.get_device_by_name("pulsectl_test_headset").unwrap();
+    assert!(headset.is_bluetooth());
+    assert_eq!(headset.bluetooth_codec().as_deref(), Some("aac"));
+    assert_eq!(
+        headset.id().bluetooth_address.as_deref(),
+        Some("00:1B:66:AA:BB:CC")
+    );
+
+    let plain = sinks.get_device_by_name(SINK_A).unwrap();
+    assert!(!plain.is_bluetooth());
+    assert_eq!(plain.bluetooth_codec(), None);
+}
+
+#[test]
+fn switching_a_missing_card_fails() {
+    let server = test_server!();
+    let mut cards = CardController::from_handler(server.connect());
+    assert!(cards.switch_to_headset_mode(4242).is_err());
+    assert!(cards.switch_to_hifi_mode(4242).is_err());
+    assert!(cards.get_bluetooth_info(4242).is_err());
+}
@@ -0,0 +1,208 @@
+// A private PulseAudio daemon for the integration tests.
+//
+// Every test starts its own `pulseaudio -n` in a fresh runtime directory with two null sinks,
+// two null sources and a couple of synthetic streams, so tests can run in parallel and never
+// touch the desktop's sound server. Tests connect through an explicit server address instead of
+// exporting PULSE_SERVER, since the environment is shared by all test threads.
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pulse::volume::{ChannelVolumes, Volume, VOLUME_NORM};
+use pulsectl::controllers::types::ApplicationInfo;
+use pulsectl::controllers::{AppControl, SinkController, SourceController};
+use pulsectl::{Handler, HandlerBuilder, PulseCtl};
+
+pub const SINK_A: &str = "pulsectl_test_sink_a";
+pub const SINK_B: &str = "pulsectl_test_sink_b";
+pub const SOURCE_A: &str = "pulsectl_test_source_a";
+pub const SOURCE_B: &str = "pulsectl_test_source_b";
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+static SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Start a test server, or return from the test when PulseAudio is not installed
+macro_rules! test_server {
+    () => {
+        match common::TestServer::start() {
+            Some(server) => server,
+            None => {
+                eprintln!("pulseaudio is not installed, skipping");
+                return;
+            }
+        }
+    };
+}
+
+pub struct TestServer {
+    daemon: Child,
+    dir: PathBuf,
+}
+
+impl TestServer {
+    /// Spawn the daemon and wait until it accepts connections,
+    /// `None` if the `pulseaudio` binary can't be run
+    pub fn start() -> Option<TestServer> {
+        let dir = env::temp_dir().join(format!(
+            "pulsectl-test-{}-{}",
+            process::id(),
+            SERVERS.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).expect("could not create the runtime directory");
+        let daemon = match spawn(&dir) {
+            Some(daemon) => daemon,
+            None => {
+                let _ = fs::remove_dir_all(&dir);
+                return None;
+            }
+        };
+        Some(TestServer { daemon, dir })
+    }
+
+    /// Stop the daemon and start a fresh one on the same socket, like a user restarting it
+    pub fn restart(&mut self) {
+        self.stop();
+        let _ = fs::remove_file(self.dir.join("native"));
+        self.daemon = spawn(&self.dir).expect("could not start pulseaudio again");
+    }
+
+    // ask the daemon to exit so it can unload its modules, kill it if it doesn't
+    fn stop(&mut self) {
+        let _ = Command::new("kill")
+            .arg("-TERM")
+            .arg(self.daemon.id().to_string())
+            .status();
+        let started = Instant::now();
+        while started.elapsed() < SHUTDOWN_TIMEOUT {
+            if let Ok(Some(_)) = self.daemon.try_wait() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        if let Ok(None) = self.daemon.try_wait() {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    pub fn address(&self) -> String {
+        format!("unix:{}", self.dir.join("native").display())
+    }
+
+    pub fn connect(&self) -> Handler {
+        HandlerBuilder::new("pulsectl-tests")
+            .server(&self.address())
+            .flags(pulse::context::flags::NOAUTOSPAWN)
+            .timeout(STARTUP_TIMEOUT)
+            .connect()
+            .expect("could not connect to the test server")
+    }
+
+    pub fn sinks(&self) -> SinkController {
+        SinkController::from_handler(self.connect())
+    }
+
+    pub fn sources(&self) -> SourceController {
+        SourceController::from_handler(self.connect())
+    }
+
+    pub fn pulse(&self) -> PulseCtl {
+        PulseCtl::from_handler(self.connect())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// run `pulseaudio` with its runtime directory and socket in `dir` and wait for the socket,
+// `None` if the binary can't be run
+fn spawn(dir: &Path) -> Option<Child> {
+    let socket = dir.join("native");
+    let mut daemon = Command::new("pulseaudio")
+        .arg("--daemonize=no")
+        .arg("-n")
+        .arg("--exit-idle-time=-1")
+        .arg("--use-pid-file=no")
+        .arg("--disable-shm=yes")
+        .arg(format!(
+            "--load=module-native-protocol-unix socket={} auth-anonymous=1",
+            socket.display()
+        ))
+        .arg(format!("--load=module-null-sink sink_name={}", SINK_A))
+        .arg(format!("--load=module-null-sink sink_name={}", SINK_B))
+        .arg(format!(
+            "--load=module-null-source source_name={}",
+            SOURCE_A
+        ))
+        .arg(format!(
+            "--load=module-null-source source_name={}",
+            SOURCE_B
+        ))
+        // a playback stream on sink A
+        .arg(format!("--load=module-sine sink={} frequency=440", SINK_A))
+        // a recording stream on source A, its playback half ends up on sink B
+        .arg(format!(
+            "--load=module-loopback source={} sink={}",
+            SOURCE_A, SINK_B
+        ))
+        .env("HOME", dir)
+        .env("XDG_RUNTIME_DIR", dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("PULSE_RUNTIME_PATH", dir)
+        .env("PULSE_STATE_PATH", dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let started = Instant::now();
+    while !socket.exists() {
+        if started.elapsed() > STARTUP_TIMEOUT {
+            let _ = daemon.kill();
+            panic!("pulseaudio did not create {}", socket.display());
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    Some(daemon)
+}
+
+/// A stereo volume at `percent` of normal
+pub fn volume(percent: f64) -> ChannelVolumes {
+    let mut volume = ChannelVolumes::default();
+    volume.set(
+        2,
+        Volume((f64::from(VOLUME_NORM.0) * percent / 100.0) as u32),
+    );
+    volume
+}
+
+/// The average of `volume` as a percentage of normal, rounded
+pub fn percent(volume: &ChannelVolumes) -> u32 {
+    (f64::from(volume.avg().0) * 100.0 / f64::from(VOLUME_NORM.0)).round() as u32
+}
+
+/// The stream created by the module whose source file is `driver`, e.g. `module-sine.c`
+pub fn find_app<C: AppControl<ApplicationInfo>>(
+    controller: &mut C,
+    driver: &str,
+) -> ApplicationInfo {
+    controller
+        .list_applications()
+        .expect("could not list applications")
+        .into_iter()
+        .find(|app| app.driver.as_deref() == Some(driver))
+        .unwrap_or_else(|| panic!("no stream created by {}", driver))
+}
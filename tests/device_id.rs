@@ -0,0 +1,84 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::identity::DeviceId;
+use pulsectl::controllers::DeviceControl;
+
+use common::{SINK_A, SINK_B};
+
+// a null sink posing as a USB device with a serial number
+fn load_usb_sink(server: &common::TestServer, name: &str) -> u32 {
+    server
+        .pulse()
+        .modules()
+        .load_module(
+            "module-null-sink",
+            &format!(
+                "sink_name={} sink_properties='device.serial=\"Vendor_DAC_0042\" \
+                 device.bus_path=\"pci-0000:00:14.0-usb-0:2:1.0\" \
+                 device.profile.name=\"analog-stereo\"'",
+                name
+            ),
+        )
+        .unwrap()
+}
+
+#[test]
+fn escapes_are_two_hex_digits() {
+    let id: DeviceId = "name=Living%20Room%3bTV".parse().unwrap();
+    assert_eq!(id.name.as_deref(), Some("Living Room;TV"));
+    for text in &["name=%+f", "name=%-1", "name=% f", "name=%zz", "name=%"] {
+        assert!(text.parse::<DeviceId>().is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn id_survives_a_round_trip_through_text() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    load_usb_sink(&server, "pulsectl_test_usb");
+    let id = sinks.get_device_by_name("pulsectl_test_usb").unwrap().id();
+    assert_eq!(id.serial.as_deref(), Some("Vendor_DAC_0042"));
+
+    let text = id.to_string();
+    assert!(!text.contains(' '));
+    let parsed: DeviceId = text.parse().unwrap();
+    assert_eq!(parsed, id);
+
+    assert!("".parse::<DeviceId>().is_err());
+    assert!("colour=blue".parse::<DeviceId>().is_err());
+    assert!("name=%4".parse::<DeviceId>().is_err());
+}
+
+#[test]
+fn name_identifies_devices_without_hardware_keys() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let a = sinks.get_device_by_name(SINK_A).unwrap();
+    let b = sinks.get_device_by_name(SINK_B).unwrap();
+    assert!(a.id().same_device(&a.id()));
+    assert!(!a.id().same_device(&b.id()));
+    assert_eq!(sinks.get_device_by_id(&b.id()).unwrap().index, b.index);
+}
+
+#[test]
+fn device_is_found_again_after_replugging() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let module = load_usb_sink(&server, "pulsectl_test_usb");
+    let before = sinks.get_device_by_name("pulsectl_test_usb").unwrap();
+    let id = before.id();
+
+    // plugged back in, with a new index and a different name
+    server.pulse().modules().unload_module(module).unwrap();
+    assert!(sinks.get_device_by_id(&id).is_err());
+    load_usb_sink(&server, "pulsectl_test_usb_renamed");
+
+    let after = sinks.get_device_by_id(&id).unwrap();
+    assert_ne!(after.index, before.index);
+    assert_eq!(after.name.as_deref(), Some("pulsectl_test_usb_renamed"));
+    assert!(id.matches(&after));
+}
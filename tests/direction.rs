@@ -0,0 +1,38 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::{AppControl, Capture, DeviceControl, DeviceController};
+
+use common::{find_app, SINK_A, SOURCE_A};
+
+#[test]
+fn one_controller_manages_both_directions() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+
+    let source = sinks.capture().get_device_by_name(SOURCE_A).unwrap();
+    assert_eq!(source.name.as_deref(), Some(SOURCE_A));
+    let loopback = find_app(&mut sinks.capture(), "module-loopback.c");
+    assert_eq!(loopback.connection_id, source.index);
+
+    // the playback side is untouched by the detour
+    assert!(sinks.get_device_by_name(SINK_A).is_ok());
+    assert!(sinks.get_device_by_name(SOURCE_A).is_err());
+}
+
+#[test]
+fn controller_over_a_borrowed_handler() {
+    let server = test_server!();
+    let mut handler = server.connect();
+    let mut sources = DeviceController::<Capture, _>::from_handler(&mut handler);
+    assert!(sources.set_device_mute_by_name(SOURCE_A, true).unwrap());
+    assert!(sources.get_device_by_name(SOURCE_A).unwrap().mute);
+    let loopback = find_app(&mut sources, "module-loopback.c");
+    assert!(sources.set_app_mute(loopback.index, true).unwrap());
+
+    // the handler is still usable once the controller is gone
+    assert!(handler.try_next_event().is_ok());
+}
@@ -0,0 +1,73 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::echo_cancel::{AecMethod, EchoCancelBuilder};
+use pulsectl::controllers::DeviceControl;
+
+use common::{SINK_A, SOURCE_A};
+
+#[test]
+fn argument_includes_every_setting() {
+    let argument = EchoCancelBuilder::new()
+        .source(SOURCE_A)
+        .method(AecMethod::Speex)
+        .aec_arg("agc", "1")
+        .noise_suppression(true)
+        .sink_name("processed")
+        .argument();
+    assert!(argument.contains("aec_method='speex'"));
+    assert!(argument.contains(&format!("source_master='{}'", SOURCE_A)));
+    assert!(argument.contains("sink_name='processed'"));
+    assert!(argument.contains(r#"aec_args='agc="1" noise_suppression="1"'"#));
+    assert!(!argument.contains("sink_master"));
+}
+
+#[test]
+fn aec_args_values_are_quoted() {
+    let argument = EchoCancelBuilder::new()
+        .aec_arg("filter", "high pass")
+        .aec_arg("comment", "it's \"quoted\"")
+        .argument();
+    assert!(argument.contains(r#"aec_args='filter="high pass" comment="it\'s \\"quoted\\""'"#));
+}
+
+#[test]
+fn processed_devices_become_defaults() {
+    let server = test_server!();
+    let pulse = server.pulse();
+    let echo_cancel = EchoCancelBuilder::new()
+        .source(SOURCE_A)
+        .sink(SINK_A)
+        .method(AecMethod::Null)
+        .source_name("pulsectl_test_aec_source")
+        .sink_name("pulsectl_test_aec_sink")
+        .make_default(true)
+        .load(&mut pulse.modules())
+        .unwrap();
+    assert_eq!(
+        echo_cancel.source.name.as_deref(),
+        Some("pulsectl_test_aec_source")
+    );
+    assert_eq!(
+        echo_cancel.sink.name.as_deref(),
+        Some("pulsectl_test_aec_sink")
+    );
+    let info = pulse.sinks().get_server_info().unwrap();
+    assert_eq!(
+        info.default_source_name.as_deref(),
+        Some("pulsectl_test_aec_source")
+    );
+    assert_eq!(
+        info.default_sink_name.as_deref(),
+        Some("pulsectl_test_aec_sink")
+    );
+
+    drop(echo_cancel);
+    assert!(pulse
+        .sources()
+        .get_device_by_name("pulsectl_test_aec_source")
+        .is_err());
+}
@@ -0,0 +1,151 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::identity::DeviceId;
+use pulsectl::controllers::{AppControl, DeviceControl};
+use pulsectl::events::{Event, Facility};
+use pulsectl::fallback::{FallbackChain, Preference};
+
+use common::{SINK_A, SINK_B};
+
+const DAC: &str = "pulsectl_test_dac";
+
+// a null sink posing as a USB DAC, returns the module index
+fn plug_dac(server: &common::TestServer) -> u32 {
+    server
+        .pulse()
+        .modules()
+        .load_module(
+            "module-null-sink",
+            &format!(
+                "sink_name={} sink_properties='device.serial=\"Vendor_DAC_0042\"'",
+                DAC
+            ),
+        )
+        .unwrap()
+}
+
+fn default_sink(server: &common::TestServer) -> String {
+    server
+        .sinks()
+        .get_server_info()
+        .unwrap()
+        .default_sink_name
+        .unwrap()
+}
+
+fn chain(server: &common::TestServer) -> FallbackChain<pulsectl::controllers::Playback> {
+    FallbackChain::new(
+        server.sinks(),
+        vec![
+            Preference::Id(DeviceId {
+                serial: Some("Vendor_DAC_0042".to_string()),
+                ..DeviceId::default()
+            }),
+            Preference::Name(SINK_B.to_string()),
+        ],
+    )
+}
+
+#[test]
+fn highest_present_preference_becomes_default() {
+    let server = test_server!();
+    server.sinks().set_default_device(SINK_A).unwrap();
+    let mut chain = chain(&server);
+
+    let chosen = chain.apply().unwrap().unwrap();
+    assert_eq!(chosen.name.as_deref(), Some(SINK_B));
+    assert_eq!(default_sink(&server), SINK_B);
+
+    plug_dac(&server);
+    assert_eq!(
+        chain.preferred().unwrap().unwrap().name.as_deref(),
+        Some(DAC)
+    );
+}
+
+#[test]
+fn streams_follow_the_preferred_device() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    sinks.set_default_device(SINK_A).unwrap();
+    let sine = common::find_app(&mut sinks, "module-sine.c");
+    let mut chain = chain(&server);
+    chain.apply().unwrap();
+
+    let module = plug_dac(&server);
+    let dac = sinks.get_device_by_name(DAC).unwrap();
+    chain
+        .handle_event(Event::New(Facility::Sink, dac.index))
+        .unwrap();
+    assert_eq!(default_sink(&server), DAC);
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        dac.index
+    );
+
+    server.pulse().modules().unload_module(module).unwrap();
+    // wherever the server rescued the stream to, pretend it picked SINK_A while already making
+    // the next preference the default, so nothing but the chain brings the stream over
+    sinks.set_default_device(SINK_B).unwrap();
+    assert!(sinks.move_app_by_name(sine.index, SINK_A).unwrap());
+    chain
+        .handle_event(Event::Removed(Facility::Sink, dac.index))
+        .unwrap();
+    let b = sinks.get_device_by_name(SINK_B).unwrap();
+    assert_eq!(default_sink(&server), SINK_B);
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        b.index
+    );
+}
+
+#[test]
+fn streams_joining_the_default_follow_it_when_it_is_unplugged() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let module = plug_dac(&server);
+    let mut chain = chain(&server);
+    chain.apply().unwrap();
+    let dac = sinks.get_device_by_name(DAC).unwrap();
+
+    // the loopback plays on SINK_B and joins the DAC after the chain looked
+    let loopback = common::find_app(&mut sinks, "module-loopback.c");
+    assert!(sinks.move_app_by_index(loopback.index, dac.index).unwrap());
+    chain
+        .handle_event(Event::Changed(Facility::SinkInput, loopback.index))
+        .unwrap();
+
+    server.pulse().modules().unload_module(module).unwrap();
+    sinks.set_default_device(SINK_B).unwrap();
+    assert!(sinks.move_app_by_name(loopback.index, SINK_A).unwrap());
+    chain
+        .handle_event(Event::Removed(Facility::Sink, dac.index))
+        .unwrap();
+    let b = sinks.get_device_by_name(SINK_B).unwrap();
+    assert_eq!(
+        sinks
+            .get_app_by_index(loopback.index)
+            .unwrap()
+            .connection_id,
+        b.index
+    );
+}
+
+#[test]
+fn absent_preferences_leave_the_default_alone() {
+    let server = test_server!();
+    server.sinks().set_default_device(SINK_A).unwrap();
+    let mut chain = FallbackChain::new(
+        server.sinks(),
+        vec![Preference::Property(
+            "device.serial".to_string(),
+            "missing".to_string(),
+        )],
+    );
+    assert!(chain.apply().unwrap().is_none());
+    assert_eq!(default_sink(&server), SINK_A);
+}
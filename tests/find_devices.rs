@@ -0,0 +1,71 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::search::{MatchField, MatchKind};
+use pulsectl::controllers::DeviceControl;
+
+use common::{SINK_A, SINK_B};
+
+const HEADPHONES: &str = "pulsectl_test_headphones";
+
+// a null sink that describes itself like a pair of USB headphones
+fn load_headphones(server: &common::TestServer) {
+    server
+        .pulse()
+        .modules()
+        .load_module(
+            "module-null-sink",
+            &format!(
+                "sink_name={} sink_properties='device.description=\"Headphones\" \
+                 device.product.name=\"USB Audio\"'",
+                HEADPHONES
+            ),
+        )
+        .unwrap();
+}
+
+#[test]
+fn exact_description_ranks_first() {
+    let server = test_server!();
+    load_headphones(&server);
+    let found = server.sinks().find_devices("headphones").unwrap();
+    assert_eq!(found[0].device.name.as_deref(), Some(HEADPHONES));
+    assert_eq!(found[0].kind, MatchKind::Exact);
+    assert_eq!(found[0].field, MatchField::Description);
+}
+
+#[test]
+fn product_name_and_fuzzy_matches() {
+    let server = test_server!();
+    load_headphones(&server);
+    let mut sinks = server.sinks();
+
+    let found = sinks.find_devices("usb").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].kind, MatchKind::Substring);
+    assert_eq!(found[0].field, MatchField::ProductName);
+
+    let found = sinks.find_devices("hdphn").unwrap();
+    assert_eq!(found[0].device.name.as_deref(), Some(HEADPHONES));
+    assert_eq!(found[0].kind, MatchKind::Fuzzy);
+}
+
+#[test]
+fn name_matches_and_misses() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let found = sinks.find_devices(SINK_B).unwrap();
+    assert_eq!(found[0].device.name.as_deref(), Some(SINK_B));
+    assert_eq!(found[0].kind, MatchKind::Exact);
+
+    // both test sinks share the prefix, the better ranked one has the lower index
+    let found = sinks.find_devices("pulsectl_test_sink").unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].device.name.as_deref(), Some(SINK_A));
+
+    assert!(sinks.find_devices("trombone").unwrap().is_empty());
+    assert!(sinks.find_devices("  ").unwrap().is_empty());
+}
@@ -0,0 +1,53 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulse::format::Encoding;
+use pulsectl::controllers::formats::Passthrough;
+use pulsectl::controllers::{AppControl, DeviceControl};
+
+use common::SINK_A;
+
+#[test]
+fn encodings_map_back_to_passthrough() {
+    for passthrough in Passthrough::all() {
+        assert_eq!(
+            Passthrough::from_encoding(passthrough.encoding()),
+            Some(passthrough)
+        );
+        assert_eq!(passthrough.format().get_encoding(), passthrough.encoding());
+    }
+    assert_eq!(Passthrough::from_encoding(Encoding::PCM), None);
+}
+
+#[test]
+fn null_sinks_refuse_passthrough() {
+    let server = test_server!();
+    server
+        .pulse()
+        .modules()
+        .load_module("module-device-restore", "")
+        .unwrap();
+    let mut sinks = server.sinks();
+    let sink = sinks.get_device_by_name(SINK_A).unwrap();
+    assert!(sink.passthrough_formats().is_empty());
+
+    // only sinks in front of real hardware can carry compressed audio
+    assert!(!sinks
+        .enable_passthrough(sink.index, &[Passthrough::Ac3, Passthrough::Dts])
+        .unwrap());
+    let sink = sinks.get_device_by_index(sink.index).unwrap();
+    assert!(!sink.supports_passthrough(Passthrough::Ac3));
+    assert!(sink.passthrough_formats().is_empty());
+}
+
+#[test]
+fn pcm_streams_are_not_passthrough() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    for app in sinks.list_applications().unwrap() {
+        assert_eq!(app.passthrough(), None);
+    }
+}
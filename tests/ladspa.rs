@@ -0,0 +1,110 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use std::env;
+use std::path::Path;
+
+use pulsectl::controllers::ladspa::LadspaPlugin;
+use pulsectl::controllers::{AppControl, DeviceControl};
+
+use common::{SINK_A, SINK_B};
+
+// the example amplifier shipped with the LADSPA SDK
+fn amp() -> LadspaPlugin {
+    LadspaPlugin::new("amp", "amp_stereo").control(1.0)
+}
+
+// whether amp.so is where the server looks for plugins
+fn amp_installed() -> bool {
+    let path = env::var("LADSPA_PATH").unwrap_or_else(|_| {
+        "/usr/lib/ladspa:/usr/local/lib/ladspa:/usr/lib64/ladspa:/usr/lib/x86_64-linux-gnu/ladspa"
+            .to_string()
+    });
+    path.split(':')
+        .any(|dir| Path::new(dir).join("amp.so").exists())
+}
+
+#[test]
+fn argument_lists_controls_in_order() {
+    let plugin = LadspaPlugin::new("caps", "Eq10X2")
+        .control(3.0)
+        .default_control()
+        .controls(&[-1.5, 0.0]);
+    let argument = plugin.argument("eq", SINK_A);
+    assert!(argument.contains("sink_name='eq'"));
+    assert!(argument.contains(&format!("master='{}'", SINK_A)));
+    assert!(argument.contains("plugin='caps' label='Eq10X2'"));
+    assert!(argument.contains("control='3,,-1.5,0'"));
+    assert!(!LadspaPlugin::new("caps", "Eq10X2")
+        .argument("eq", SINK_A)
+        .contains("control"));
+}
+
+#[test]
+fn filter_sink_is_a_device_and_survives_new_controls() {
+    let server = test_server!();
+    if !amp_installed() {
+        eprintln!("the LADSPA SDK plugins are not installed, skipping");
+        return;
+    }
+    let pulse = server.pulse();
+    let mut filter = pulse
+        .modules()
+        .create_ladspa_sink("pulsectl_test_filter", SINK_B, &amp())
+        .unwrap();
+    let mut sinks = pulse.sinks();
+    let device = filter.device().unwrap();
+    assert!(sinks
+        .list_devices()
+        .unwrap()
+        .iter()
+        .any(|d| d.index == device.index));
+
+    let sine = common::find_app(&mut sinks, "module-sine.c");
+    sinks.move_app_by_index(sine.index, device.index).unwrap();
+    sinks.set_default_device(filter.name()).unwrap();
+
+    let module = filter.module();
+    filter.set_control(0, 0.5).unwrap();
+    assert_ne!(filter.module(), module);
+    assert_eq!(filter.plugin().controls, vec![Some(0.5)]);
+    let device = filter.device().unwrap();
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        device.index
+    );
+    assert_eq!(
+        sinks
+            .get_server_info()
+            .unwrap()
+            .default_sink_name
+            .as_deref(),
+        Some("pulsectl_test_filter")
+    );
+
+    // the amplifier has a single control, the filter comes back with the previous one
+    assert!(filter
+        .set_controls(vec![Some(1.0), Some(2.0), Some(3.0)])
+        .is_err());
+    assert!(filter.module().is_some());
+    assert_eq!(filter.plugin().controls, vec![Some(0.5)]);
+    let device = filter.device().unwrap();
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        device.index
+    );
+    assert_eq!(
+        sinks
+            .get_server_info()
+            .unwrap()
+            .default_sink_name
+            .as_deref(),
+        Some("pulsectl_test_filter")
+    );
+
+    assert!(filter.remove().unwrap());
+    assert!(sinks.get_device_by_name("pulsectl_test_filter").is_err());
+}
@@ -0,0 +1,53 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulse::time::MicroSeconds;
+use pulsectl::controllers::{AppControl, CardController};
+
+// latencies move with every block the server plays
+fn close(a: MicroSeconds, b: MicroSeconds) -> bool {
+    a.0.max(b.0) - a.0.min(b.0) <= 50_000
+}
+
+#[test]
+fn report_covers_every_stream() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let apps = sinks.list_applications().unwrap();
+    let report = sinks.latency_report().unwrap();
+    assert_eq!(report.len(), apps.len());
+
+    let sine = common::find_app(&mut sinks, "module-sine.c");
+    let entry = report.iter().find(|r| r.stream == sine.index).unwrap();
+    assert_eq!(entry.device, sine.connection_id);
+    // both were measured a moment apart while the sink keeps playing
+    assert!(close(entry.buffer, sine.buffer_usec));
+    assert!(close(entry.device_latency, sine.connection_usec));
+    // null sinks don't belong to a card
+    assert_eq!(entry.port_offset, None);
+}
+
+#[test]
+fn both_directions_are_reported() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let loopback = common::find_app(&mut sources, "module-loopback.c");
+    let report = sources.latency_report().unwrap();
+    assert!(report.iter().any(|r| r.stream == loopback.index));
+    assert!(sources.playback().latency_report().unwrap().len() >= 2);
+}
+
+#[test]
+fn offsets_of_missing_ports_are_errors() {
+    let server = test_server!();
+    let mut cards = CardController::from_handler(server.connect());
+    assert!(cards
+        .get_port_latency_offset("pulsectl_no_such_card", "hdmi-output-0")
+        .is_err());
+    assert!(cards
+        .set_port_latency_offset("pulsectl_no_such_card", "hdmi-output-0", 50_000)
+        .is_err());
+}
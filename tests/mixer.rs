@@ -0,0 +1,86 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pulsectl::controllers::DeviceControl;
+use pulsectl::events::{Event, Facility};
+use pulsectl::mixer::MixerState;
+
+use common::{percent, volume, SINK_A, SINK_B, SOURCE_A};
+
+// apply events until `done` holds, panicking after a few seconds
+fn update_until<F: Fn(&MixerState) -> bool>(mixer: &mut MixerState, done: F) {
+    let started = Instant::now();
+    while !done(mixer) {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "mixer state never caught up"
+        );
+        mixer.update().unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn loads_the_current_state() {
+    let server = test_server!();
+    let mixer = MixerState::new(server.pulse()).unwrap();
+    assert!(mixer.sink_by_name(SINK_A).is_some());
+    assert!(mixer.sink_by_name(SINK_B).is_some());
+    assert!(mixer.source_by_name(SOURCE_A).is_some());
+    assert_eq!(mixer.sink_inputs().count(), 2);
+    assert!(mixer.source_outputs().count() >= 1);
+    assert!(mixer.default_sink().is_some());
+}
+
+#[test]
+fn follows_changes_made_elsewhere() {
+    let server = test_server!();
+    let mut mixer = MixerState::new(server.pulse()).unwrap();
+    let generation = mixer.generation();
+    let index = mixer.sink_by_name(SINK_A).unwrap().index;
+
+    let mut sinks = server.sinks();
+    sinks.set_device_volume_by_index(index, &volume(35.0));
+    update_until(&mut mixer, |m| {
+        percent(&m.sink(index).unwrap().volume) == 35
+    });
+    assert!(mixer.generation() > generation);
+
+    sinks.set_default_device(SINK_B).unwrap();
+    update_until(&mut mixer, |m| {
+        m.default_sink().and_then(|s| s.name.as_deref()) == Some(SINK_B)
+    });
+}
+
+#[test]
+fn notices_devices_coming_and_going() {
+    let server = test_server!();
+    let mut mixer = MixerState::new(server.pulse()).unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let seen_ref = seen.clone();
+    mixer.on_change(move |event| seen_ref.borrow_mut().push(*event));
+
+    let mut modules = server.pulse().modules();
+    let module = modules
+        .load_module("module-null-sink", "sink_name=pulsectl_test_mixer")
+        .unwrap();
+    update_until(&mut mixer, |m| {
+        m.sink_by_name("pulsectl_test_mixer").is_some()
+    });
+    let index = mixer.sink_by_name("pulsectl_test_mixer").unwrap().index;
+    assert!(seen.borrow().contains(&Event::New(Facility::Sink, index)));
+
+    modules.unload_module(module).unwrap();
+    update_until(&mut mixer, |m| m.sink(index).is_none());
+    assert!(seen
+        .borrow()
+        .contains(&Event::Removed(Facility::Sink, index)));
+}
@@ -0,0 +1,164 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use pulsectl::controllers::{AppControl, DeviceControl, SinkController};
+use pulsectl::events::{subscription_masks, Event, Facility};
+use pulsectl::HandlerBuilder;
+
+use common::{find_app, SINK_A, SINK_B, SOURCE_A};
+
+#[test]
+fn controllers_share_one_connection() {
+    let server = test_server!();
+    let pulse = server.pulse();
+    let mut sinks = pulse.sinks();
+    let mut sources = pulse.sources();
+    assert!(Rc::ptr_eq(&sinks.handler, &sources.handler));
+
+    // both stay usable side by side
+    let sine = find_app(&mut sinks, "module-sine.c");
+    let loopback = find_app(&mut sources, "module-loopback.c");
+    let sink_b = sinks.get_device_by_name(SINK_B).unwrap().index;
+    assert!(sinks.move_app_by_index(sine.index, sink_b).unwrap());
+    assert!(sources.set_app_mute(loopback.index, true).unwrap());
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        sink_b
+    );
+    assert!(sources.get_device_by_name(SOURCE_A).is_ok());
+}
+
+#[test]
+fn null_sinks_have_no_cards() {
+    let server = test_server!();
+    assert!(server.pulse().cards().list_cards().unwrap().is_empty());
+}
+
+#[test]
+fn load_and_unload_module() {
+    let server = test_server!();
+    let pulse = server.pulse();
+    let mut modules = pulse.modules();
+
+    let index = modules
+        .load_module("module-null-sink", "sink_name=pulsectl_test_loaded")
+        .unwrap();
+    let module = modules.get_module_by_index(index).unwrap();
+    assert_eq!(module.name.as_deref(), Some("module-null-sink"));
+    assert!(modules
+        .list_modules()
+        .unwrap()
+        .iter()
+        .any(|m| m.index == index));
+    assert!(pulse
+        .sinks()
+        .get_device_by_name("pulsectl_test_loaded")
+        .is_ok());
+
+    assert!(modules.unload_module(index).unwrap());
+    assert!(pulse
+        .sinks()
+        .get_device_by_name("pulsectl_test_loaded")
+        .is_err());
+    assert!(pulse.sinks().get_device_by_name(SINK_A).is_ok());
+}
+
+#[test]
+fn loading_an_unknown_module_fails() {
+    let server = test_server!();
+    assert!(server
+        .pulse()
+        .modules()
+        .load_module("module-does-not-exist", "")
+        .is_err());
+}
+
+#[test]
+fn reconnecting_to_a_stopped_daemon_times_out() {
+    let server = test_server!();
+    let mut handler = HandlerBuilder::new("pulsectl-tests")
+        .server(&server.address())
+        .flags(pulse::context::flags::NOAUTOSPAWN)
+        .timeout(Duration::from_millis(500))
+        .reconnect(true)
+        .connect()
+        .unwrap();
+    drop(server);
+
+    let started = Instant::now();
+    let error = loop {
+        // the first iterations may still see the context alive while it notices the hangup
+        match handler.try_next_event() {
+            Ok(_) => assert!(started.elapsed() < Duration::from_secs(5)),
+            Err(error) => break error,
+        }
+    };
+    assert!(error.is_timeout(), "{:?}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn polling_for_events_does_not_wait_for_a_stopped_daemon() {
+    let server = test_server!();
+    // no timeout, so only the polling call itself bounds the reconnect attempts
+    let mut handler = HandlerBuilder::new("pulsectl-tests")
+        .server(&server.address())
+        .flags(pulse::context::flags::NOAUTOSPAWN)
+        .reconnect(true)
+        .connect()
+        .unwrap();
+    drop(server);
+
+    let started = Instant::now();
+    let error = loop {
+        match handler.try_next_event() {
+            Ok(_) => assert!(started.elapsed() < Duration::from_secs(5)),
+            Err(error) => break error,
+        }
+    };
+    assert!(error.is_timeout(), "{:?}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn handlers_reconnect_to_a_restarted_daemon() {
+    let mut server = test_server!();
+    let handler = HandlerBuilder::new("pulsectl-tests")
+        .server(&server.address())
+        .flags(pulse::context::flags::NOAUTOSPAWN)
+        .timeout(Duration::from_secs(10))
+        .reconnect(true)
+        .connect()
+        .unwrap();
+    let mut sinks = SinkController::from_handler(handler);
+    sinks.handler.subscribe(subscription_masks::SINK).unwrap();
+
+    server.restart();
+    loop {
+        if sinks.handler.next_event().unwrap() == Event::Reconnected {
+            break;
+        }
+    }
+
+    // the subscription was renewed on the new connection
+    server
+        .pulse()
+        .modules()
+        .load_module("module-null-sink", "sink_name=pulsectl_test_after_restart")
+        .unwrap();
+    let added = loop {
+        if let Event::New(Facility::Sink, index) = sinks.handler.next_event().unwrap() {
+            break index;
+        }
+    };
+    let device = sinks.get_device_by_index(added).unwrap();
+    assert_eq!(device.name.as_deref(), Some("pulsectl_test_after_restart"));
+    assert!(sinks.get_device_by_name(SINK_A).is_ok());
+    assert!(!sinks.list_applications().unwrap().is_empty());
+}
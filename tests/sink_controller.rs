@@ -0,0 +1,191 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::{AppControl, DeviceControl};
+
+use common::{find_app, percent, volume, SINK_A, SINK_B};
+
+const SINE: &str = "module-sine.c";
+
+#[test]
+fn list_devices_contains_null_sinks() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let names: Vec<String> = sinks
+        .list_devices()
+        .unwrap()
+        .into_iter()
+        .filter_map(|d| d.name)
+        .collect();
+    assert!(names.contains(&SINK_A.to_string()));
+    assert!(names.contains(&SINK_B.to_string()));
+}
+
+#[test]
+fn get_device_by_index_and_name_agree() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let by_name = sinks.get_device_by_name(SINK_A).unwrap();
+    let by_index = sinks.get_device_by_index(by_name.index).unwrap();
+    assert_eq!(by_index.name.as_deref(), Some(SINK_A));
+    assert!(sinks.get_device_by_name("no_such_sink").is_err());
+}
+
+#[test]
+fn set_default_device() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    assert!(sinks.set_default_device(SINK_B).unwrap());
+    assert_eq!(
+        sinks.get_default_device().unwrap().name.as_deref(),
+        Some(SINK_B)
+    );
+    assert!(sinks.set_default_device(SINK_A).unwrap());
+    assert_eq!(
+        sinks.get_default_device().unwrap().name.as_deref(),
+        Some(SINK_A)
+    );
+}
+
+#[test]
+fn set_device_volume() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let index = sinks.get_device_by_name(SINK_A).unwrap().index;
+
+    sinks.set_device_volume_by_index(index, &volume(30.0));
+    assert_eq!(
+        percent(&sinks.get_device_by_index(index).unwrap().volume),
+        30
+    );
+
+    sinks.set_device_volume_by_name(SINK_A, &volume(70.0));
+    assert_eq!(
+        percent(&sinks.get_device_by_index(index).unwrap().volume),
+        70
+    );
+}
+
+#[test]
+fn change_device_volume_by_percent() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let index = sinks.get_device_by_name(SINK_A).unwrap().index;
+    sinks.set_device_volume_by_index(index, &volume(50.0));
+
+    sinks.increase_device_volume_by_percent(index, 0.1);
+    assert_eq!(
+        percent(&sinks.get_device_by_index(index).unwrap().volume),
+        60
+    );
+
+    sinks.decrease_device_volume_by_percent(index, 0.2);
+    assert_eq!(
+        percent(&sinks.get_device_by_index(index).unwrap().volume),
+        40
+    );
+}
+
+#[test]
+fn set_device_mute() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let index = sinks.get_device_by_name(SINK_A).unwrap().index;
+
+    assert!(sinks.set_device_mute_by_index(index, true).unwrap());
+    assert!(sinks.get_device_by_index(index).unwrap().mute);
+
+    assert!(sinks.set_device_mute_by_name(SINK_A, false).unwrap());
+    assert!(!sinks.get_device_by_index(index).unwrap().mute);
+}
+
+#[test]
+fn set_missing_device_port_fails() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let index = sinks.get_device_by_name(SINK_A).unwrap().index;
+    // null sinks have no ports to switch to
+    assert!(!sinks
+        .set_device_port_by_index(index, "no-such-port")
+        .unwrap());
+    assert!(!sinks
+        .set_device_port_by_name(SINK_A, "no-such-port")
+        .unwrap());
+}
+
+#[test]
+fn list_and_get_applications() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let sine = find_app(&mut sinks, SINE);
+    let sink_a = sinks.get_device_by_name(SINK_A).unwrap();
+    assert_eq!(sine.connection_id, sink_a.index);
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().index,
+        sine.index
+    );
+}
+
+#[test]
+fn set_app_volume() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let sine = find_app(&mut sinks, SINE);
+
+    assert!(sinks.set_app_volume(sine.index, &volume(25.0)).unwrap());
+    assert_eq!(
+        percent(&sinks.get_app_by_index(sine.index).unwrap().volume),
+        25
+    );
+
+    sinks.increase_app_volume_by_percent(sine.index, 0.1);
+    assert_eq!(
+        percent(&sinks.get_app_by_index(sine.index).unwrap().volume),
+        35
+    );
+
+    sinks.decrease_app_volume_by_percent(sine.index, 0.05);
+    assert_eq!(
+        percent(&sinks.get_app_by_index(sine.index).unwrap().volume),
+        30
+    );
+}
+
+#[test]
+fn move_app() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let sine = find_app(&mut sinks, SINE);
+    let sink_a = sinks.get_device_by_name(SINK_A).unwrap().index;
+    let sink_b = sinks.get_device_by_name(SINK_B).unwrap().index;
+
+    assert!(sinks.move_app_by_index(sine.index, sink_b).unwrap());
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        sink_b
+    );
+
+    assert!(sinks.move_app_by_name(sine.index, SINK_A).unwrap());
+    assert_eq!(
+        sinks.get_app_by_index(sine.index).unwrap().connection_id,
+        sink_a
+    );
+}
+
+#[test]
+fn set_app_mute() {
+    let server = test_server!();
+    let mut sinks = server.sinks();
+    let sine = find_app(&mut sinks, SINE);
+
+    assert!(sinks.set_app_mute(sine.index, true).unwrap());
+    assert!(sinks.get_app_by_index(sine.index).unwrap().mute);
+    // muting the stream must not touch the sink it plays on
+    assert!(!sinks.get_device_by_name(SINK_A).unwrap().mute);
+
+    assert!(sinks.set_app_mute(sine.index, false).unwrap());
+    assert!(!sinks.get_app_by_index(sine.index).unwrap().mute);
+}
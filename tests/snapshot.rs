@@ -0,0 +1,150 @@
+#![cfg(feature = "mock")]
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+use pulse::volume::{ChannelVolumes, Volume};
+use pulsectl::controllers::mock::{Call, MockController};
+use pulsectl::controllers::types::{ApplicationInfoBuilder, DeviceInfoBuilder};
+use pulsectl::snapshot::MixerSnapshot;
+
+fn stereo(volume: u32) -> ChannelVolumes {
+    let mut volumes = ChannelVolumes::default();
+    volumes.set(2, Volume(volume));
+    volumes
+}
+
+fn sinks() -> MockController {
+    MockController::new()
+        .with_device(DeviceInfoBuilder::new(0, "speakers").build())
+        .with_device(
+            DeviceInfoBuilder::new(1, "headphones")
+                .volume(stereo(30000))
+                .mute(true)
+                .build(),
+        )
+        .with_default("headphones")
+}
+
+#[test]
+fn capture_records_devices_and_streams() {
+    let mut sinks = sinks().with_app(
+        ApplicationInfoBuilder::new(7, "Playback")
+            .application("Music Player")
+            .connection_id(1)
+            .volume(stereo(20000))
+            .build(),
+    );
+    let mut sources = MockController::new();
+
+    let snapshot = MixerSnapshot::capture(&mut sinks, &mut sources).unwrap();
+
+    assert_eq!(snapshot.sinks.len(), 2);
+    let headphones = &snapshot.sinks[1];
+    assert_eq!(headphones.name, "headphones");
+    assert_eq!(headphones.volume, stereo(30000));
+    assert!(headphones.mute);
+    assert!(headphones.is_default);
+    assert!(!snapshot.sinks[0].is_default);
+    assert!(snapshot.sources.is_empty());
+
+    assert_eq!(snapshot.playback.len(), 1);
+    let player = &snapshot.playback[0];
+    assert_eq!(player.key, "Music Player");
+    assert_eq!(player.device.as_deref(), Some("headphones"));
+    assert_eq!(player.volume, stereo(20000));
+}
+
+#[test]
+fn streams_without_an_application_are_keyed_by_name() {
+    let mut sinks = sinks().with_app(ApplicationInfoBuilder::new(7, "Playback").build());
+    let mut sources = MockController::new();
+
+    let snapshot = MixerSnapshot::capture(&mut sinks, &mut sources).unwrap();
+
+    assert_eq!(snapshot.playback[0].key, "Playback");
+}
+
+#[test]
+fn restore_puts_back_what_was_captured() {
+    let mut sinks = sinks().with_app(
+        ApplicationInfoBuilder::new(7, "Playback")
+            .application("Music Player")
+            .connection_id(1)
+            .build(),
+    );
+    let mut sources = MockController::new();
+    let snapshot = MixerSnapshot::capture(&mut sinks, &mut sources).unwrap();
+
+    // the stream reconnects under a new index on the other device and both devices change
+    sinks.applications = vec![ApplicationInfoBuilder::new(9, "Playback")
+        .application("Music Player")
+        .mute(true)
+        .build()];
+    sinks.devices[1].volume = stereo(65536);
+    sinks.devices[1].mute = false;
+    sinks.default_device = Some("speakers".to_string());
+
+    let report = snapshot.restore(&mut sinks, &mut sources).unwrap();
+
+    assert!(report.is_complete(), "{:?}", report);
+    assert_eq!(sinks.default_device.as_deref(), Some("headphones"));
+    assert_eq!(sinks.devices[1].volume, stereo(30000));
+    assert!(sinks.devices[1].mute);
+    assert_eq!(sinks.applications[0].connection_id, 1);
+    assert!(!sinks.applications[0].mute);
+}
+
+#[test]
+fn saved_streams_pair_up_with_live_streams_one_to_one() {
+    let mut sinks = sinks()
+        .with_app(
+            ApplicationInfoBuilder::new(7, "Tab 1")
+                .application("Browser")
+                .volume(stereo(10000))
+                .build(),
+        )
+        .with_app(
+            ApplicationInfoBuilder::new(8, "Tab 2")
+                .application("Browser")
+                .volume(stereo(50000))
+                .build(),
+        );
+    let mut sources = MockController::new();
+    let snapshot = MixerSnapshot::capture(&mut sinks, &mut sources).unwrap();
+
+    // only one of the two streams came back
+    sinks.applications = vec![ApplicationInfoBuilder::new(12, "Tab 1")
+        .application("Browser")
+        .build()];
+    sinks.take_calls();
+
+    let report = snapshot.restore(&mut sinks, &mut sources).unwrap();
+
+    assert_eq!(report.unmatched_apps, vec!["Browser".to_string()]);
+    assert_eq!(sinks.applications[0].volume, stereo(10000));
+    let volume_calls = sinks
+        .calls()
+        .iter()
+        .filter(|call| matches!(call, Call::SetAppVolume(..)))
+        .count();
+    assert_eq!(volume_calls, 1);
+}
+
+#[test]
+fn missing_devices_and_streams_are_reported() {
+    let mut sinks = sinks().with_app(
+        ApplicationInfoBuilder::new(7, "Playback")
+            .application("Music Player")
+            .build(),
+    );
+    let mut sources = MockController::new();
+    let snapshot = MixerSnapshot::capture(&mut sinks, &mut sources).unwrap();
+
+    sinks.devices.remove(1);
+    sinks.applications.clear();
+
+    let report = snapshot.restore(&mut sinks, &mut sources).unwrap();
+
+    assert_eq!(report.unmatched_devices, vec!["headphones".to_string()]);
+    assert_eq!(report.unmatched_apps, vec!["Music Player".to_string()]);
+}
@@ -0,0 +1,200 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use pulsectl::controllers::{AppControl, DeviceControl};
+
+use common::{find_app, percent, volume, SINK_A, SOURCE_A, SOURCE_B};
+
+const LOOPBACK: &str = "module-loopback.c";
+
+#[test]
+fn list_devices_contains_null_sources() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let names: Vec<String> = sources
+        .list_devices()
+        .unwrap()
+        .into_iter()
+        .filter_map(|d| d.name)
+        .collect();
+    assert!(names.contains(&SOURCE_A.to_string()));
+    assert!(names.contains(&SOURCE_B.to_string()));
+    // sources, not sinks
+    assert!(!names.contains(&SINK_A.to_string()));
+}
+
+#[test]
+fn get_device_by_index_and_name_agree() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let by_name = sources.get_device_by_name(SOURCE_A).unwrap();
+    let by_index = sources.get_device_by_index(by_name.index).unwrap();
+    assert_eq!(by_index.name.as_deref(), Some(SOURCE_A));
+    assert!(sources.get_device_by_name("no_such_source").is_err());
+}
+
+#[test]
+fn set_default_device() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    assert!(sources.set_default_device(SOURCE_B).unwrap());
+    assert_eq!(
+        sources.get_default_device().unwrap().name.as_deref(),
+        Some(SOURCE_B)
+    );
+    assert!(sources.set_default_device(SOURCE_A).unwrap());
+    assert_eq!(
+        sources.get_default_device().unwrap().name.as_deref(),
+        Some(SOURCE_A)
+    );
+}
+
+#[test]
+fn set_device_volume() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let index = sources.get_device_by_name(SOURCE_A).unwrap().index;
+
+    sources.set_device_volume_by_index(index, &volume(30.0));
+    assert_eq!(
+        percent(&sources.get_device_by_index(index).unwrap().volume),
+        30
+    );
+
+    sources.set_device_volume_by_name(SOURCE_A, &volume(70.0));
+    assert_eq!(
+        percent(&sources.get_device_by_index(index).unwrap().volume),
+        70
+    );
+}
+
+#[test]
+fn change_device_volume_by_percent() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let index = sources.get_device_by_name(SOURCE_A).unwrap().index;
+    sources.set_device_volume_by_index(index, &volume(50.0));
+
+    sources.increase_device_volume_by_percent(index, 0.1);
+    assert_eq!(
+        percent(&sources.get_device_by_index(index).unwrap().volume),
+        60
+    );
+
+    sources.decrease_device_volume_by_percent(index, 0.2);
+    assert_eq!(
+        percent(&sources.get_device_by_index(index).unwrap().volume),
+        40
+    );
+}
+
+#[test]
+fn set_device_mute() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let index = sources.get_device_by_name(SOURCE_A).unwrap().index;
+
+    assert!(sources.set_device_mute_by_index(index, true).unwrap());
+    assert!(sources.get_device_by_index(index).unwrap().mute);
+
+    assert!(sources.set_device_mute_by_name(SOURCE_A, false).unwrap());
+    assert!(!sources.get_device_by_index(index).unwrap().mute);
+}
+
+#[test]
+fn set_missing_device_port_fails() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let index = sources.get_device_by_name(SOURCE_A).unwrap().index;
+    assert!(!sources
+        .set_device_port_by_index(index, "no-such-port")
+        .unwrap());
+    assert!(!sources
+        .set_device_port_by_name(SOURCE_A, "no-such-port")
+        .unwrap());
+}
+
+#[test]
+fn list_and_get_applications() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let loopback = find_app(&mut sources, LOOPBACK);
+    let source_a = sources.get_device_by_name(SOURCE_A).unwrap();
+    assert_eq!(loopback.connection_id, source_a.index);
+    assert_eq!(
+        sources.get_app_by_index(loopback.index).unwrap().index,
+        loopback.index
+    );
+}
+
+#[test]
+fn set_app_volume() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let loopback = find_app(&mut sources, LOOPBACK);
+
+    assert!(sources
+        .set_app_volume(loopback.index, &volume(25.0))
+        .unwrap());
+    assert_eq!(
+        percent(&sources.get_app_by_index(loopback.index).unwrap().volume),
+        25
+    );
+
+    sources.increase_app_volume_by_percent(loopback.index, 0.1);
+    assert_eq!(
+        percent(&sources.get_app_by_index(loopback.index).unwrap().volume),
+        35
+    );
+
+    sources.decrease_app_volume_by_percent(loopback.index, 0.05);
+    assert_eq!(
+        percent(&sources.get_app_by_index(loopback.index).unwrap().volume),
+        30
+    );
+}
+
+#[test]
+fn move_app() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let loopback = find_app(&mut sources, LOOPBACK);
+    let source_a = sources.get_device_by_name(SOURCE_A).unwrap().index;
+    let source_b = sources.get_device_by_name(SOURCE_B).unwrap().index;
+
+    assert!(sources.move_app_by_index(loopback.index, source_b).unwrap());
+    assert_eq!(
+        sources
+            .get_app_by_index(loopback.index)
+            .unwrap()
+            .connection_id,
+        source_b
+    );
+
+    assert!(sources.move_app_by_name(loopback.index, SOURCE_A).unwrap());
+    assert_eq!(
+        sources
+            .get_app_by_index(loopback.index)
+            .unwrap()
+            .connection_id,
+        source_a
+    );
+}
+
+#[test]
+fn set_app_mute() {
+    let server = test_server!();
+    let mut sources = server.sources();
+    let loopback = find_app(&mut sources, LOOPBACK);
+
+    assert!(sources.set_app_mute(loopback.index, true).unwrap());
+    assert!(sources.get_app_by_index(loopback.index).unwrap().mute);
+    // muting the stream must not touch the source it records from
+    assert!(!sources.get_device_by_name(SOURCE_A).unwrap().mute);
+
+    assert!(sources.set_app_mute(loopback.index, false).unwrap());
+    assert!(!sources.get_app_by_index(loopback.index).unwrap().mute);
+}
@@ -0,0 +1,84 @@
+extern crate libpulse_binding as pulse;
+extern crate pulsectl;
+
+#[macro_use]
+mod common;
+
+use std::time::Duration;
+
+use pulse::channelmap;
+use pulsectl::controllers::DeviceControl;
+
+use common::{SINK_A, SINK_B, SOURCE_B};
+
+fn module_loaded(server: &common::TestServer, index: u32) -> bool {
+    server
+        .pulse()
+        .modules()
+        .list_modules()
+        .unwrap()
+        .iter()
+        .any(|m| m.index == index)
+}
+
+#[test]
+fn null_sink_is_unloaded_on_drop() {
+    let server = test_server!();
+    let pulse = server.pulse();
+    let mut modules = pulse.modules();
+    let mut map = channelmap::Map::default();
+    map.init_mono();
+
+    let mut device = modules
+        .create_null_sink("pulsectl_test_virtual", "Virtual \"test\" sink", Some(&map))
+        .unwrap();
+    let module = device.module().unwrap();
+    assert_eq!(device.sink_name(), Some("pulsectl_test_virtual"));
+    assert_eq!(device.source_name(), None);
+    let sink = device.sink().unwrap();
+    assert_eq!(sink.description.as_deref(), Some("Virtual \"test\" sink"));
+    assert_eq!(sink.channel_map.len(), 1);
+
+    drop(device);
+    assert!(!module_loaded(&server, module));
+    assert!(pulse
+        .sinks()
+        .get_device_by_name("pulsectl_test_virtual")
+        .is_err());
+}
+
+#[test]
+fn destroy_and_into_module() {
+    let server = test_server!();
+    let mut modules = server.pulse().modules();
+
+    let loopback = modules
+        .create_loopback(SOURCE_B, SINK_A, Duration::from_millis(50))
+        .unwrap();
+    let module = loopback.module().unwrap();
+    assert!(loopback.sink_name().is_none());
+    assert!(loopback.destroy().unwrap());
+    assert!(!module_loaded(&server, module));
+
+    let kept = modules
+        .create_null_sink("pulsectl_test_kept", "Kept", None)
+        .unwrap();
+    let module = kept.into_module();
+    assert!(module_loaded(&server, module));
+}
+
+#[test]
+fn combined_and_remapped_sinks() {
+    let server = test_server!();
+    let mut modules = server.pulse().modules();
+
+    let mut combined = modules.create_combined_sink(&[SINK_A, SINK_B]).unwrap();
+    assert!(combined.sink().is_ok());
+    assert!(modules.create_combined_sink(&[]).is_err());
+
+    let mut map = channelmap::Map::default();
+    map.init_stereo();
+    let mut remapped = modules.create_remap_sink(SINK_A, &map).unwrap();
+    let sink = remapped.sink().unwrap();
+    assert_eq!(sink.channel_map.len(), 2);
+}